@@ -3,8 +3,11 @@ use std::{mem::swap, sync::MutexGuard};
 use bytemuck::{Pod, Zeroable};
 
 use crate::{
+    check::Checker,
+    checksum::{is_checksum_page, Checksums},
     free_list::FreeList,
     page::{PageNr, Pager},
+    retention::{RetainedSnapshot, Retention, MAX_RETAINED},
 };
 
 #[derive(Clone, Copy, Zeroable, Pod)]
@@ -13,12 +16,84 @@ pub struct AllocatorState {
     previous_free: FreeList,
     current_free: FreeList,
     last_page: u32,
+    retained_len: u32,
+    retained: [RetainedSnapshot; MAX_RETAINED],
+}
+
+#[derive(Clone, Copy)]
+pub enum Target {
+    Current,
+    Dead(usize),
 }
 
 impl AllocatorState {
-    pub fn swap(&mut self) {
+    pub fn from_free_lists(
+        previous_free: FreeList,
+        current_free: FreeList,
+        last_page: u32,
+    ) -> Self {
+        Self {
+            previous_free,
+            current_free,
+            last_page,
+            ..Self::default()
+        }
+    }
+
+    pub fn swap(&mut self, release: bool) {
         swap(&mut self.previous_free, &mut self.current_free);
-        self.current_free.reset_front();
+        if release {
+            self.current_free.reset_front();
+        }
+    }
+
+    pub fn first_page() -> PageNr {
+        Self::default().last_page + 1
+    }
+
+    pub fn last_page(&self) -> PageNr {
+        self.last_page
+    }
+
+    pub fn free_lists(&self) -> (FreeList, FreeList) {
+        (self.previous_free, self.current_free)
+    }
+
+    pub fn retained(&self) -> &[RetainedSnapshot] {
+        &self.retained[..self.retained_len as usize]
+    }
+
+    pub fn retain(&mut self, snapshot: RetainedSnapshot) {
+        assert!(self.retained().len() < MAX_RETAINED);
+        self.retained[self.retained_len as usize] = snapshot;
+        self.retained_len += 1;
+    }
+
+    pub fn release(&mut self, index: usize) -> RetainedSnapshot {
+        let snapshot = self.retained[index];
+        self.retained.copy_within(index + 1.., index);
+        self.retained_len -= 1;
+        self.retained[self.retained_len as usize] = RetainedSnapshot::default();
+        snapshot
+    }
+
+    pub fn restore(&mut self, index: usize) {
+        let retained = self.retained;
+        *self = Self {
+            last_page: self.last_page,
+            retained_len: index as u32 + 1,
+            retained,
+            ..Self::default()
+        };
+        self.retained[index].dead = FreeList::default();
+    }
+
+    pub(crate) fn check(&self, checker: &mut Checker) {
+        self.previous_free.check(checker);
+        self.current_free.check(checker);
+        for snapshot in self.retained() {
+            snapshot.dead.check(checker);
+        }
     }
 }
 
@@ -28,24 +103,37 @@ impl Default for AllocatorState {
             previous_free: FreeList::default(),
             current_free: FreeList::default(),
             last_page: 1,
+            retained_len: 0,
+            retained: Zeroable::zeroed(),
         }
     }
 }
 
 pub struct Allocator<'a> {
     state: MutexGuard<'a, AllocatorState>,
+    retention: MutexGuard<'a, Retention>,
     pager: &'a Pager,
+    checksums: Option<&'a Checksums>,
     append: Vec<u32>,
     prepend: Vec<u32>,
+    park: Vec<(usize, u32)>,
 }
 
 impl<'a> Allocator<'a> {
-    pub fn new(state: MutexGuard<'a, AllocatorState>, pager: &'a Pager) -> Self {
+    pub fn new(
+        state: MutexGuard<'a, AllocatorState>,
+        retention: MutexGuard<'a, Retention>,
+        pager: &'a Pager,
+        checksums: Option<&'a Checksums>,
+    ) -> Self {
         Self {
             state,
+            retention,
             pager,
+            checksums,
             append: Vec::new(),
             prepend: Vec::new(),
+            park: Vec::new(),
         }
     }
 
@@ -58,49 +146,98 @@ impl<'a> Allocator<'a> {
             nr
         } else {
             self.state.last_page += 1;
+            if self.checksums.is_some() && is_checksum_page(self.state.last_page) {
+                self.state.last_page += 1;
+            }
             self.state.last_page
         };
-        unsafe { self.pager.enable_write(nr) };
+        self.enable_write(nr);
         nr
     }
 
     pub unsafe fn deallocate(&mut self, nr: PageNr) {
         if self.pager.can_write(nr) {
             self.append.push(nr);
+        } else if let Some(index) = self.retaining(nr) {
+            self.park.push((index, nr));
         } else {
-            self.prepend.push(nr);
-            if let Some(old_nr) = self.state.current_free.shift_front(self.pager) {
-                self.append.push(old_nr);
-            }
+            self.retain_page(nr);
         }
     }
 
     pub unsafe fn reallocate(&mut self, nr: PageNr) -> PageNr {
+        if let Some(checksums) = self.checksums {
+            checksums.copy(nr);
+        }
+        if let Some(index) = self.retaining(nr) {
+            self.park.push((index, nr));
+            return self.allocate();
+        }
         self.prepend.push(nr);
         if let Some(new_nr) = self.state.current_free.shift_front(self.pager) {
-            self.pager.enable_write(new_nr);
+            self.enable_write(new_nr);
             new_nr
         } else {
             self.allocate()
         }
     }
 
+    pub unsafe fn release(&mut self, index: usize) {
+        let snapshot = self.state.release(index);
+        self.retention.remove(index);
+        for nr in snapshot.dead.entries(self.pager) {
+            match index.checked_sub(1) {
+                Some(older) if self.retention.retains(older, nr) => self.park.push((older, nr)),
+                _ => self.retain_page(nr),
+            }
+        }
+        for nr in snapshot.dead.pages(self.pager) {
+            self.deallocate(nr);
+        }
+    }
+
+    fn enable_write(&self, nr: PageNr) {
+        if let Some(checksums) = self.checksums {
+            checksums.write(nr);
+        }
+        unsafe { self.pager.enable_write(nr) };
+    }
+
     pub fn pager(&self) -> &'a Pager {
         self.pager
     }
 
-    pub fn current_free(&mut self) -> &mut FreeList {
-        &mut self.state.current_free
+    pub fn free_list(&mut self, target: Target) -> &mut FreeList {
+        match target {
+            Target::Current => &mut self.state.current_free,
+            Target::Dead(index) => &mut self.state.retained[index].dead,
+        }
+    }
+
+    fn retaining(&self, nr: PageNr) -> Option<usize> {
+        self.retention
+            .newest()
+            .filter(|index| self.retention.retains(*index, nr))
+    }
+
+    unsafe fn retain_page(&mut self, nr: PageNr) {
+        self.prepend.push(nr);
+        if let Some(old_nr) = self.state.current_free.shift_front(self.pager) {
+            self.append.push(old_nr);
+        }
     }
 
     unsafe fn resolve(&mut self) {
-        while !(self.prepend.is_empty() && self.append.is_empty()) {
+        while !(self.prepend.is_empty() && self.append.is_empty() && self.park.is_empty()) {
             let mut prepend = Vec::new();
             swap(&mut prepend, &mut self.prepend);
             FreeList::prepend(self, prepend);
             let mut append = Vec::new();
             swap(&mut append, &mut self.append);
             FreeList::append(self, append);
+            let mut park = Vec::new();
+            swap(&mut park, &mut self.park);
+            FreeList::park(self, park);
         }
     }
 }
@@ -0,0 +1,329 @@
+use std::{
+    cmp::Ordering,
+    io::{self, ErrorKind, Read, Write},
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    check::Checker,
+    compact::Copier,
+    container::Walk,
+    cursor::{reallocate, PageLookup},
+    lock::Lock,
+    page::{PageNr, PAGE_SIZE},
+    reference::DatabaseRef,
+    tree::{Iter, Tree, TreeGuard},
+};
+
+/// Longest key a [`BlobTree`] accepts. Keys are stored inline in the tree
+/// as a fixed 130 byte slot (a two byte length followed by 128 bytes), so
+/// short keys are padded to the full size.
+pub const MAX_BLOB_KEY_LEN: usize = 128;
+
+const INLINE_VALUE_LEN: usize = 64;
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub(crate) struct BlobKey {
+    len: u16,
+    bytes: [u8; MAX_BLOB_KEY_LEN],
+}
+
+impl BlobKey {
+    fn new(key: &[u8]) -> Option<Self> {
+        if key.len() > MAX_BLOB_KEY_LEN {
+            return None;
+        }
+        let mut this = Self::zeroed();
+        this.len = key.len() as u16;
+        this.bytes[..key.len()].copy_from_slice(key);
+        Some(this)
+    }
+
+    fn bound(
+        key: &[u8],
+        exact: fn(Self) -> Bound<Self>,
+        truncated: fn(Self) -> Bound<Self>,
+    ) -> Bound<Self> {
+        if key.len() > MAX_BLOB_KEY_LEN {
+            truncated(Self::new(&key[..MAX_BLOB_KEY_LEN]).unwrap())
+        } else {
+            exact(Self::new(key).unwrap())
+        }
+    }
+
+    fn lower_bound(bound: Bound<&&[u8]>) -> Bound<Self> {
+        match bound {
+            Bound::Included(key) => Self::bound(key, Bound::Included, Bound::Excluded),
+            Bound::Excluded(key) => Self::bound(key, Bound::Excluded, Bound::Excluded),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    fn upper_bound(bound: Bound<&&[u8]>) -> Bound<Self> {
+        match bound {
+            Bound::Included(key) => Self::bound(key, Bound::Included, Bound::Included),
+            Bound::Excluded(key) => Self::bound(key, Bound::Excluded, Bound::Included),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl PartialEq for BlobKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for BlobKey {}
+
+impl PartialOrd for BlobKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BlobKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub(crate) struct BlobValue {
+    len: u32,
+    root: PageNr,
+    inline: [u8; INLINE_VALUE_LEN],
+}
+
+impl BlobValue {
+    fn pages(&self) -> usize {
+        if self.is_inline() {
+            0
+        } else {
+            (self.len as usize + PAGE_SIZE - 1) / PAGE_SIZE
+        }
+    }
+
+    fn is_inline(&self) -> bool {
+        self.len as usize <= INLINE_VALUE_LEN
+    }
+
+    fn write(bytes: &[u8], lock: &Lock) -> Self {
+        let mut value = Self::zeroed();
+        value.len = bytes.len() as u32;
+        if value.is_inline() {
+            value.inline[..bytes.len()].copy_from_slice(bytes);
+        } else {
+            let pages = value.pages();
+            reallocate(&mut value.root, 0, pages, lock);
+            let mut lookup = PageLookup::Invalid;
+            for (index, chunk) in bytes.chunks(PAGE_SIZE).enumerate() {
+                let page = unsafe {
+                    lookup
+                        .get_mut(&mut value.root, pages, index, lock)
+                        .as_mut()
+                        .unwrap()
+                };
+                page[..chunk.len()].copy_from_slice(chunk);
+            }
+        }
+        value
+    }
+
+    fn read(&self, lock: &Lock) -> Vec<u8> {
+        let len = self.len as usize;
+        if self.is_inline() {
+            return self.inline[..len].to_vec();
+        }
+        let pages = self.pages();
+        let mut lookup = PageLookup::Invalid;
+        let mut bytes = Vec::with_capacity(len);
+        for index in 0..pages {
+            let page = lookup.get(self.root, pages, index, lock);
+            let n = (len - bytes.len()).min(PAGE_SIZE);
+            bytes.extend_from_slice(&page[..n]);
+        }
+        bytes
+    }
+
+    fn free(mut self, lock: &Lock) {
+        if !self.is_inline() {
+            let pages = self.pages();
+            reallocate(&mut self.root, pages, 0, lock)
+        }
+    }
+}
+
+/// Tree of byte string keys of at most [`MAX_BLOB_KEY_LEN`] bytes to byte
+/// string values. Values up to 64 bytes are stored inline, larger ones in
+/// separate pages.
+pub struct BlobTree {
+    tree: Tree<BlobKey, BlobValue>,
+}
+
+impl BlobTree {
+    pub fn new(database: DatabaseRef) -> Self {
+        Self {
+            tree: Tree::new(database),
+        }
+    }
+
+    pub fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        Ok(Self {
+            tree: Tree::deserialize_with::<Self, _>(reader, database)?,
+        })
+    }
+
+    pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.tree.serialize(writer)
+    }
+
+    pub fn read(&self) -> ReadBlobTreeGuard<'_> {
+        BlobTreeGuard {
+            tree: self.tree.read(),
+        }
+    }
+
+    pub fn write(&mut self) -> WriteBlobTreeGuard<'_> {
+        BlobTreeGuard {
+            tree: self.tree.write(),
+        }
+    }
+}
+
+impl Walk for BlobTree {
+    fn check(checker: &mut Checker, root: PageNr, _: u64) {
+        Tree::<BlobKey, BlobValue>::check_with(checker, root, &mut |checker, value| {
+            checker.visit_pages(value.root, value.pages())
+        })
+    }
+
+    fn copy(copier: &mut Copier, root: PageNr, _: u64) -> PageNr {
+        Tree::<BlobKey, BlobValue>::copy_with(copier, root, &mut |copier, value| {
+            value.root = copier.copy_pages(value.root, value.pages())
+        })
+    }
+}
+
+impl Drop for BlobTree {
+    fn drop(&mut self) {
+        let guard = self.read();
+        if !guard.tree.lock().is_closing() {
+            guard.free_values();
+        }
+    }
+}
+
+pub struct BlobTreeGuard<'a, R: Deref<Target = PageNr>> {
+    tree: TreeGuard<'a, R, BlobKey, BlobValue>,
+}
+
+pub type ReadBlobTreeGuard<'a> = BlobTreeGuard<'a, &'a PageNr>;
+pub type WriteBlobTreeGuard<'a> = BlobTreeGuard<'a, &'a mut PageNr>;
+
+impl<'a, R: Deref<Target = PageNr>> BlobTreeGuard<'a, R> {
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let key = BlobKey::new(key)?;
+        self.tree
+            .get(&key)
+            .map(|value| value.read(self.tree.lock()))
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        BlobKey::new(key).map_or(false, |key| self.tree.get(&key).is_some())
+    }
+
+    pub fn iter(&self) -> BlobIter<'_, 'a> {
+        BlobIter {
+            iter: self.tree.iter(),
+            lock: self.tree.lock(),
+        }
+    }
+
+    pub fn range<'k>(&self, range: impl RangeBounds<&'k [u8]>) -> BlobIter<'_, 'a> {
+        let start = BlobKey::lower_bound(range.start_bound());
+        let end = BlobKey::upper_bound(range.end_bound());
+        BlobIter {
+            iter: self.tree.range((start, end)),
+            lock: self.tree.lock(),
+        }
+    }
+
+    pub fn first(&self) -> Option<(&[u8], Vec<u8>)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(&[u8], Vec<u8>)> {
+        self.iter().next_back()
+    }
+
+    fn free_values(&self) {
+        for (_, value) in self.tree.iter() {
+            value.free(self.tree.lock())
+        }
+    }
+}
+
+impl<'a, R: DerefMut<Target = PageNr>> BlobTreeGuard<'a, R> {
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let key = BlobKey::new(key).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("blob key is longer than {} bytes", MAX_BLOB_KEY_LEN),
+            )
+        })?;
+        if value.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "blob value is larger than 4 GiB",
+            ));
+        }
+        let value = BlobValue::write(value, self.tree.lock());
+        Ok(self.tree.insert(key, value).map(|old| {
+            let bytes = old.read(self.tree.lock());
+            old.free(self.tree.lock());
+            bytes
+        }))
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let old = self.tree.remove(&BlobKey::new(key)?)?;
+        let bytes = old.read(self.tree.lock());
+        old.free(self.tree.lock());
+        Some(bytes)
+    }
+
+    pub fn clear(&mut self) {
+        self.free_values();
+        self.tree.clear();
+    }
+}
+
+pub struct BlobIter<'a, 'b> {
+    iter: Iter<'a, 'b, BlobKey, BlobValue>,
+    lock: &'a Lock<'b>,
+}
+
+impl<'a, 'b> Iterator for BlobIter<'a, 'b> {
+    type Item = (&'a [u8], Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        Some((key.as_bytes(), value.read(self.lock)))
+    }
+}
+
+impl<'a, 'b> DoubleEndedIterator for BlobIter<'a, 'b> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next_back()?;
+        Some((key.as_bytes(), value.read(self.lock)))
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    allocator::AllocatorState,
+    checksum::is_checksum_page,
+    cursor,
+    lock::Lock,
+    page::{PageNr, NULL_PAGE_NR},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    OutOfRange(PageNr),
+    Missing(PageNr),
+    DoublyReferenced(PageNr),
+    Leaked(PageNr),
+    TooLarge(PageNr),
+    Unordered(PageNr),
+    Underfilled(PageNr),
+    Overfilled(PageNr),
+    Unbalanced(PageNr),
+    ChecksumMismatch(PageNr),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange(nr) => write!(f, "page {} is referenced but out of range", nr),
+            Self::Missing(NULL_PAGE_NR) => write!(f, "container root page is missing"),
+            Self::Missing(nr) => write!(f, "page {} references a missing page", nr),
+            Self::DoublyReferenced(nr) => write!(f, "page {} is referenced more than once", nr),
+            Self::Leaked(nr) => write!(f, "page {} is neither referenced nor free", nr),
+            Self::TooLarge(nr) => write!(f, "container at page {} has too many pages", nr),
+            Self::Unordered(nr) => write!(f, "tree node {} has keys out of order", nr),
+            Self::Underfilled(nr) => write!(f, "tree node {} is underfilled", nr),
+            Self::Overfilled(nr) => write!(f, "tree node {} is overfilled", nr),
+            Self::Unbalanced(nr) => write!(f, "tree leaf {} is at the wrong depth", nr),
+            Self::ChecksumMismatch(nr) => write!(f, "page {} does not match its checksum", nr),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckReport {
+    pub version: u64,
+    pub pages: usize,
+    pub used: usize,
+    pub free: usize,
+    pub retained: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl CheckReport {
+    pub fn is_consistent(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PageState {
+    Unused,
+    Reserved,
+    Used,
+    Free,
+    Retained,
+}
+
+pub(crate) struct Checker<'a, 'b> {
+    lock: &'a Lock<'b>,
+    last_page: PageNr,
+    states: Vec<PageState>,
+    problems: Vec<Problem>,
+    referenced: usize,
+}
+
+impl<'a, 'b> Checker<'a, 'b> {
+    pub fn new(lock: &'a Lock<'b>, allocator: &AllocatorState, pages: usize) -> Self {
+        let mut states = vec![PageState::Unused; pages];
+        for (nr, state) in states.iter_mut().enumerate() {
+            if nr < AllocatorState::first_page() as usize
+                || lock.has_checksums() && is_checksum_page(nr as PageNr)
+            {
+                *state = PageState::Reserved;
+            }
+        }
+        Self {
+            lock,
+            last_page: allocator.last_page(),
+            states,
+            problems: Vec::new(),
+            referenced: 0,
+        }
+    }
+
+    pub fn lock(&self) -> &'a Lock<'b> {
+        self.lock
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn problem(&mut self, problem: Problem) {
+        self.problems.push(problem)
+    }
+
+    pub fn reference(&mut self, nr: PageNr) -> bool {
+        if !self.mark(nr, PageState::Used) {
+            return false;
+        }
+        if !self.lock.matches_checksum(nr) {
+            self.problem(Problem::ChecksumMismatch(nr));
+            return false;
+        }
+        self.referenced += 1;
+        true
+    }
+
+    pub fn referenced(&self) -> usize {
+        self.referenced
+    }
+
+    pub fn free(&mut self, nr: PageNr, retained: bool) {
+        if retained {
+            self.mark(nr, PageState::Retained);
+        } else {
+            self.mark(nr, PageState::Free);
+        }
+    }
+
+    pub fn visit_pages(&mut self, root: PageNr, pages: usize) {
+        cursor::check(root, pages, self)
+    }
+
+    fn mark(&mut self, nr: PageNr, state: PageState) -> bool {
+        let index = nr as usize;
+        if nr > self.last_page || index >= self.states.len() {
+            self.problem(Problem::OutOfRange(nr));
+            return false;
+        }
+        match self.states[index] {
+            PageState::Unused => {
+                self.states[index] = state;
+                true
+            }
+            PageState::Reserved => {
+                self.problem(Problem::OutOfRange(nr));
+                false
+            }
+            _ => {
+                self.problem(Problem::DoublyReferenced(nr));
+                false
+            }
+        }
+    }
+
+    pub fn into_used(self) -> Vec<bool> {
+        self.states
+            .into_iter()
+            .map(|state| state == PageState::Used)
+            .collect()
+    }
+
+    pub fn finish(mut self, version: u64) -> CheckReport {
+        let last = self.states.len().min(self.last_page as usize + 1);
+        for nr in 0..last {
+            if self.states[nr] == PageState::Unused {
+                self.problem(Problem::Leaked(nr as PageNr))
+            }
+        }
+        let states = &self.states;
+        let count = |state| states.iter().filter(|s| **s == state).count();
+        CheckReport {
+            version,
+            pages: last,
+            used: count(PageState::Used),
+            free: count(PageState::Free),
+            retained: count(PageState::Retained),
+            problems: self.problems,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashSet,
+    io,
+    mem::{size_of, take},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use bytemuck::{cast_mut, cast_ref};
+use crc32fast::Hasher;
+use tracing::error;
+
+use crate::page::{Page, PageNr, Pager, PAGE_SIZE};
+
+type PageChecksum = u32;
+
+type ChecksumPage = [PageChecksum; CHECKSUMS_PER_PAGE];
+
+const CHECKSUMS_PER_PAGE: usize = PAGE_SIZE / size_of::<PageChecksum>();
+
+const STRIDE: PageNr = CHECKSUMS_PER_PAGE as PageNr + 1;
+
+pub fn is_checksum_page(nr: PageNr) -> bool {
+    nr % STRIDE == 1
+}
+
+fn location(nr: PageNr) -> (PageNr, usize) {
+    let offset = (nr - 1) % STRIDE;
+    (nr - offset, offset as usize - 1)
+}
+
+fn checksum(page: &Page) -> PageChecksum {
+    let mut hasher = Hasher::new();
+    hasher.update(&page[..]);
+    hasher.finalize()
+}
+
+fn mismatch_error(nr: PageNr) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("checksum mismatch in page {}", nr),
+    )
+}
+
+pub struct Checksums {
+    verify: AtomicBool,
+    verified: Mutex<HashSet<PageNr>>,
+    copied: Mutex<Vec<PageNr>>,
+    written: Mutex<Vec<PageNr>>,
+    mismatch: Mutex<Option<PageNr>>,
+}
+
+impl Checksums {
+    pub fn new() -> Self {
+        Self {
+            verify: AtomicBool::new(cfg!(debug_assertions)),
+            verified: Mutex::new(HashSet::new()),
+            copied: Mutex::new(Vec::new()),
+            written: Mutex::new(Vec::new()),
+            mismatch: Mutex::new(None),
+        }
+    }
+
+    pub fn set_verify(&self, verify: bool) {
+        self.verify.store(verify, Ordering::Relaxed)
+    }
+
+    pub fn is_verifying(&self) -> bool {
+        self.verify.load(Ordering::Relaxed)
+    }
+
+    pub unsafe fn matches(nr: PageNr, pager: &Pager) -> bool {
+        let (table_nr, index) = location(nr);
+        cast_ref::<Page, ChecksumPage>(pager.page(table_nr))[index] == checksum(pager.page(nr))
+    }
+
+    /// Verifies a committed page before it is read. Pages that do not match
+    /// are not remembered as verified, so every read of them fails.
+    pub unsafe fn read(&self, nr: PageNr, pager: &Pager) -> io::Result<()> {
+        if !self.is_verifying() || self.verified.lock().unwrap().contains(&nr) {
+            return Ok(());
+        }
+        if Self::matches(nr, pager) {
+            self.verified.lock().unwrap().insert(nr);
+            Ok(())
+        } else {
+            self.mismatch(nr);
+            Err(mismatch_error(nr))
+        }
+    }
+
+    pub fn copy(&self, nr: PageNr) {
+        self.copied.lock().unwrap().push(nr)
+    }
+
+    pub fn write(&self, nr: PageNr) {
+        self.written.lock().unwrap().push(nr)
+    }
+
+    fn mismatch(&self, nr: PageNr) {
+        error!("checksum mismatch in page {}", nr);
+        self.mismatch.lock().unwrap().get_or_insert(nr);
+    }
+
+    /// Checks the pages copied since the last snapshot, whether or not reads
+    /// are verified, and rehashes the written ones. Verified pages stay
+    /// verified, as committed pages only change once they are written again.
+    pub unsafe fn update(&self, pager: &Pager) -> io::Result<()> {
+        for nr in self.copied.lock().unwrap().drain(..) {
+            if !Self::matches(nr, pager) {
+                self.mismatch(nr)
+            }
+        }
+        if let Some(nr) = self.mismatch.lock().unwrap().take() {
+            return Err(mismatch_error(nr));
+        }
+        let mut written = take(&mut *self.written.lock().unwrap());
+        written.sort_unstable();
+        written.dedup();
+        for nr in written {
+            if pager.can_write(nr) {
+                let (table_nr, index) = location(nr);
+                let checksum = checksum(pager.page(nr));
+                cast_mut::<Page, ChecksumPage>(pager.page_mut(table_nr))[index] = checksum;
+            }
+        }
+        Ok(())
+    }
+}
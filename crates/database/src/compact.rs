@@ -0,0 +1,31 @@
+use crate::{
+    cursor,
+    lock::Lock,
+    page::{Page, PageNr},
+};
+
+pub(crate) struct Copier<'a, 'b> {
+    source: &'a Lock<'b>,
+    target: &'a Lock<'b>,
+}
+
+impl<'a, 'b> Copier<'a, 'b> {
+    pub fn new(source: &'a Lock<'b>, target: &'a Lock<'b>) -> Self {
+        Self { source, target }
+    }
+
+    pub fn copy_page(&mut self, page_nr: PageNr) -> (PageNr, &'a mut Page) {
+        let new_nr = self.target.allocate();
+        let page = unsafe { self.target.try_page_mut(new_nr).unwrap() };
+        *page = *unsafe { self.source.page(page_nr) };
+        (new_nr, page)
+    }
+
+    pub fn target(&self) -> &'a Lock<'b> {
+        self.target
+    }
+
+    pub fn copy_pages(&mut self, root: PageNr, pages: usize) -> PageNr {
+        cursor::copy(root, pages, self)
+    }
+}
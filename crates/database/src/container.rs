@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::{check::Checker, compact::Copier, page::PageNr};
+
+pub(crate) trait Walk {
+    fn check(checker: &mut Checker, root: PageNr, len: u64);
+
+    fn copy(copier: &mut Copier, root: PageNr, len: u64) -> PageNr;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Container {
+    check: fn(&mut Checker, PageNr, u64),
+    copy: fn(&mut Copier, PageNr, u64) -> PageNr,
+    root: PageNr,
+    len: u64,
+}
+
+impl Container {
+    pub fn new<W: Walk>(root: PageNr, len: u64) -> Self {
+        Self {
+            check: W::check,
+            copy: W::copy,
+            root,
+            len,
+        }
+    }
+
+    pub fn root(&self) -> PageNr {
+        self.root
+    }
+
+    pub fn check(&self, checker: &mut Checker) {
+        (self.check)(checker, self.root, self.len)
+    }
+
+    pub fn copy(&self, copier: &mut Copier) -> PageNr {
+        (self.copy)(copier, self.root, self.len)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Registry {
+    containers: Option<Vec<Container>>,
+    fields: Vec<(&'static str, usize)>,
+    relocations: HashMap<PageNr, PageNr>,
+}
+
+impl Registry {
+    pub fn track(&mut self) {
+        self.containers = Some(Vec::new());
+        self.fields.clear();
+    }
+
+    pub fn begin_field(&mut self, name: &'static str) {
+        if let Some(containers) = self.containers.as_ref() {
+            self.fields.push((name, containers.len()))
+        }
+    }
+
+    pub fn register(&mut self, container: Container) -> PageNr {
+        if let Some(containers) = self.containers.as_mut() {
+            containers.push(container)
+        }
+        self.relocations
+            .get(&container.root)
+            .copied()
+            .unwrap_or(container.root)
+    }
+
+    pub fn relocate(&mut self, from: PageNr, to: PageNr) {
+        self.relocations.insert(from, to);
+    }
+
+    pub fn take(&mut self) -> Vec<Container> {
+        self.containers.take().unwrap_or_default()
+    }
+
+    /// Takes the tracked containers grouped by the top-level field of the
+    /// object that registered them. Containers registered before the first
+    /// field come first, under an empty name.
+    pub fn take_fields(&mut self) -> Vec<(&'static str, Vec<Container>)> {
+        let mut containers = self.take();
+        let mut fields = Vec::with_capacity(self.fields.len() + 1);
+        for (name, start) in self.fields.drain(..).rev() {
+            fields.push((name, containers.split_off(start)));
+        }
+        if !containers.is_empty() {
+            fields.push(("", containers));
+        }
+        fields.reverse();
+        fields
+    }
+}
@@ -1,8 +1,10 @@
-use std::mem::size_of;
+use std::{io, mem::size_of};
 
 use bytemuck::{cast_mut, cast_ref};
 
 use crate::{
+    check::{Checker, Problem},
+    compact::Copier,
     lock::Lock,
     page::{Page, PageNr, NULL_PAGE_NR, PAGE_SIZE},
 };
@@ -11,12 +13,10 @@ type IndirectPage = [PageNr; FAN_OUT as usize];
 
 const FAN_OUT: usize = PAGE_SIZE / size_of::<PageNr>();
 
+const MAX_PAGES: usize = PageNr::MAX as usize;
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum PageLevel {
-    L0,
-    L1,
-    L2,
-}
+struct PageLevel(u32);
 
 #[derive(Clone, Copy)]
 struct PageIndex(usize, PageLevel);
@@ -27,7 +27,7 @@ impl PageIndex {
         Self(index, PageLevel::from_pages(pages).unwrap())
     }
 
-    pub const fn index(self) -> usize {
+    pub fn index(self) -> usize {
         self.0 / self.1.child_pages()
     }
 
@@ -55,12 +55,25 @@ impl PageLookup {
         index: usize,
         lock: &'a Lock,
     ) -> &'a Page {
+        match self.try_get(root_nr, pages, index, lock) {
+            Ok(page) => page,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_get<'a>(
+        &mut self,
+        root_nr: PageNr,
+        pages: usize,
+        index: usize,
+        lock: &'a Lock,
+    ) -> io::Result<&'a Page> {
         let page_nr = match *self {
             Self::Immutable(key, page_nr) => {
                 if key == index {
                     page_nr
                 } else {
-                    let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock);
+                    let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock)?;
                     *self = PageLookup::Immutable(index, page_nr);
                     page_nr
                 }
@@ -69,18 +82,18 @@ impl PageLookup {
                 if key == index {
                     page_nr
                 } else {
-                    let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock);
+                    let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock)?;
                     *self = PageLookup::Immutable(index, page_nr);
                     page_nr
                 }
             }
             Self::Invalid => {
-                let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock);
+                let page_nr = find_page(root_nr, PageIndex::new(index, pages), lock)?;
                 *self = PageLookup::Immutable(index, page_nr);
                 page_nr
             }
         };
-        unsafe { lock.page(page_nr) }
+        unsafe { lock.try_page(page_nr) }
     }
 
     pub unsafe fn get_mut<'a>(
@@ -120,15 +133,15 @@ impl PageLookup {
     }
 }
 
-fn find_page(page_nr: PageNr, index: PageIndex, lock: &Lock) -> PageNr {
+fn find_page(page_nr: PageNr, index: PageIndex, lock: &Lock) -> io::Result<PageNr> {
     if index.is_indirect() {
         find_page(
-            cast_ref::<Page, IndirectPage>(unsafe { lock.page(page_nr) })[index.index()],
+            cast_ref::<Page, IndirectPage>(unsafe { lock.try_page(page_nr)? })[index.index()],
             index.child(),
             lock,
         )
     } else {
-        page_nr
+        Ok(page_nr)
     }
 }
 
@@ -147,46 +160,34 @@ fn find_page_mut(page_nr: &mut PageNr, index: PageIndex, lock: &Lock) -> PageNr
 
 impl PageLevel {
     pub const fn is_indirect(self) -> bool {
-        match self {
-            Self::L0 => false,
-            Self::L1 => true,
-            Self::L2 => true,
-        }
+        self.0 > 0
     }
 
     pub fn from_pages(pages: usize) -> Option<Self> {
-        match pages {
-            0 => None,
-            1 => Some(Self::L0),
-            2..=2048 => Some(Self::L1),
-            2049..=4194304 => Some(Self::L2),
-            _ => panic!(),
+        if pages == 0 {
+            return None;
+        }
+        let mut level = Self(0);
+        while level.capacity() < pages {
+            level = Self(level.0 + 1);
         }
+        Some(level)
     }
 
-    pub const fn child(self) -> Option<Self> {
-        match self {
-            Self::L0 => None,
-            Self::L1 => Some(Self::L0),
-            Self::L2 => Some(Self::L1),
-        }
+    pub fn child(self) -> Option<Self> {
+        self.0.checked_sub(1).map(Self)
     }
 
     pub fn parent(this: Option<Self>) -> Self {
-        match this {
-            None => Self::L0,
-            Some(Self::L0) => Self::L1,
-            Some(Self::L1) => Self::L2,
-            Some(Self::L2) => panic!(),
-        }
+        this.map_or(Self(0), |level| Self(level.0 + 1))
     }
 
-    pub const fn child_pages(self) -> usize {
-        match self {
-            Self::L0 => 0,
-            Self::L1 => 1,
-            Self::L2 => 2048,
-        }
+    pub fn child_pages(self) -> usize {
+        self.child().map_or(0, Self::capacity)
+    }
+
+    fn capacity(self) -> usize {
+        FAN_OUT.saturating_pow(self.0)
     }
 }
 
@@ -330,9 +331,8 @@ fn deallocate_from(page_nr: &mut PageNr, from: usize, level: PageLevel, lock: &L
 }
 
 fn deallocate_full(page_nr: PageNr, mut index: usize, level: PageLevel, lock: &Lock) {
-    let page = unsafe { lock.page(page_nr) };
     if level.is_indirect() {
-        let page = cast_ref::<Page, IndirectPage>(page);
+        let page = cast_ref::<Page, IndirectPage>(unsafe { lock.page(page_nr) });
         while index < FAN_OUT && page[index] != NULL_PAGE_NR {
             deallocate_full(page[index], 0, level.child().unwrap(), lock);
             index += 1;
@@ -340,3 +340,93 @@ fn deallocate_full(page_nr: PageNr, mut index: usize, level: PageLevel, lock: &L
     }
     unsafe { lock.deallocate(page_nr) }
 }
+
+pub(crate) fn check(root_nr: PageNr, pages: usize, checker: &mut Checker) {
+    if pages > MAX_PAGES {
+        checker.problem(Problem::TooLarge(root_nr));
+    } else if let Some(level) = PageLevel::from_pages(pages) {
+        check_level(NULL_PAGE_NR, root_nr, pages, level, checker);
+    }
+}
+
+fn check_level(
+    parent_nr: PageNr,
+    page_nr: PageNr,
+    pages: usize,
+    level: PageLevel,
+    checker: &mut Checker,
+) {
+    if page_nr == NULL_PAGE_NR {
+        checker.problem(Problem::Missing(parent_nr));
+        return;
+    }
+    if !checker.reference(page_nr) || !level.is_indirect() {
+        return;
+    }
+    let page = cast_ref::<Page, IndirectPage>(unsafe { checker.lock().page(page_nr) });
+    let child_level = level.child().unwrap();
+    let child_pages = level.child_pages();
+    let children = (pages + child_pages - 1) / child_pages;
+    for (index, child) in page.iter().take(children).enumerate() {
+        let pages = (pages - index * child_pages).min(child_pages);
+        check_level(page_nr, *child, pages, child_level, checker);
+    }
+}
+
+pub(crate) fn copy(root_nr: PageNr, pages: usize, copier: &mut Copier) -> PageNr {
+    match PageLevel::from_pages(pages) {
+        Some(level) => copy_level(root_nr, pages, level, copier),
+        None => NULL_PAGE_NR,
+    }
+}
+
+fn copy_level(page_nr: PageNr, pages: usize, level: PageLevel, copier: &mut Copier) -> PageNr {
+    let (new_nr, page) = copier.copy_page(page_nr);
+    if level.is_indirect() {
+        let page = cast_mut::<Page, IndirectPage>(page);
+        let child_level = level.child().unwrap();
+        let child_pages = level.child_pages();
+        let children = (pages + child_pages - 1) / child_pages;
+        for (index, child) in page.iter_mut().take(children).enumerate() {
+            let pages = (pages - index * child_pages).min(child_pages);
+            *child = copy_level(*child, pages, child_level, copier);
+        }
+    }
+    new_nr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageLevel, FAN_OUT, MAX_PAGES};
+
+    fn level(pages: usize) -> Option<u32> {
+        PageLevel::from_pages(pages).map(|level| level.0)
+    }
+
+    #[test]
+    fn levels_at_boundaries() {
+        assert_eq!(level(0), None);
+        assert_eq!(level(1), Some(0));
+        assert_eq!(level(2), Some(1));
+        assert_eq!(level(FAN_OUT), Some(1));
+        assert_eq!(level(FAN_OUT + 1), Some(2));
+        assert_eq!(level(FAN_OUT * FAN_OUT), Some(2));
+        assert_eq!(level(FAN_OUT * FAN_OUT + 1), Some(3));
+        assert_eq!(level(MAX_PAGES), Some(3));
+        assert_eq!(level(usize::MAX), Some(6));
+    }
+
+    #[test]
+    fn parent_child_and_capacity() {
+        assert!(PageLevel(0).child().is_none());
+        assert_eq!(PageLevel(3).child().map(|level| level.0), Some(2));
+        assert_eq!(PageLevel::parent(None).0, 0);
+        assert_eq!(PageLevel::parent(Some(PageLevel(2))).0, 3);
+        assert_eq!(PageLevel(0).child_pages(), 0);
+        assert_eq!(PageLevel(1).child_pages(), 1);
+        assert_eq!(PageLevel(2).child_pages(), FAN_OUT);
+        assert_eq!(PageLevel(2).capacity(), FAN_OUT * FAN_OUT);
+        assert!(PageLevel(3).capacity() >= MAX_PAGES);
+        assert_eq!(PageLevel(u32::MAX).capacity(), usize::MAX);
+    }
+}
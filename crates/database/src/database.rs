@@ -1,54 +1,506 @@
 use std::{
-    fs::OpenOptions,
-    io::{self, Seek, SeekFrom},
+    fs::{self, rename, OpenOptions},
+    io::{self, ErrorKind, Seek, SeekFrom},
+    mem::replace,
+    ops::Deref,
     path::Path,
 };
 
-use crate::{file::File, object::Object, raw::RawDatabase, reference::DatabaseRef};
+use crate::{
+    allocator::AllocatorState,
+    check::{CheckReport, Checker},
+    compact::Copier,
+    file::{File, FileHeader},
+    flock::{lock_reader, lock_writer},
+    object::Object,
+    page::PAGE_SIZE,
+    raw::RawDatabase,
+    reference::DatabaseRef,
+    retention::{RetainedSnapshot, MAX_RETAINED},
+    stats::{ContainerStats, FieldStats, PageStats, Stats},
+};
 
 pub struct Database {
     file: File,
     database: DatabaseRef,
+    committed: Option<FileHeader>,
+    retention: usize,
 }
 
 impl Database {
+    /// Opens the database at `path` for writing. While it is open, the writer
+    /// lock is held on a `<path>.lock` file next to it, which is left behind
+    /// afterwards and can be removed once no process uses the database.
     pub fn open<T: Object>(path: impl AsRef<Path>) -> io::Result<(Self, T)> {
+        let path = path.as_ref();
+        let writer_lock = lock_writer(path)?;
         let file = OpenOptions::new().read(true).write(true).open(path)?;
-        let (raw, header) = RawDatabase::open(file, &T::format())?;
+        let format = T::format();
+        let (raw, header) = RawDatabase::open(file, writer_lock, &format)?;
         let database = DatabaseRef::new(raw);
-        let file = File::from_header(header, database.clone());
+        let mut file = File::from_header(header, database.clone());
+        let mut committed = Some(header);
+        if database.format_version() < format.version {
+            file = Self::migrate::<T>(file, &database)?;
+            committed = None;
+        }
         let content = T::deserialize(&mut file.read(), database.clone())?;
-        Ok((Self { file, database }, content))
+        Ok((
+            Self {
+                file,
+                database,
+                committed,
+                retention: MAX_RETAINED,
+            },
+            content,
+        ))
+    }
+
+    fn migrate<T: Object>(mut file: File, database: &DatabaseRef) -> io::Result<File> {
+        let migrations = T::migrations();
+        let version = T::format().version;
+        for from_version in database.format_version()..version {
+            let migration = migrations.get(from_version)?;
+            let mut migrated = File::new(database.clone());
+            database.lock().close();
+            let result = migration(&mut file.read(), &mut migrated.write(), database.clone());
+            database.lock().reopen();
+            result?;
+            file.write().set_len(0);
+            file = migrated;
+        }
+        database.set_format_version(version);
+        Ok(file)
+    }
+
+    pub fn open_read_only<T: Object>(path: impl AsRef<Path>) -> io::Result<ReadOnly<T>> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        lock_reader(&file)?;
+        let (raw, header) = RawDatabase::open_read_only(file, &T::format())?;
+        let database = DatabaseRef::new(raw);
+        let file = File::from_header(header, database.clone());
+        let content = T::deserialize(&mut file.read(), database)?;
+        Ok(ReadOnly(content))
+    }
+
+    pub fn check<T: Object>(path: &Path) -> io::Result<CheckReport> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        lock_reader(&file)?;
+        let (raw, header) = RawDatabase::open_read_only(file, &T::format())?;
+        let version = raw.version();
+        Self::walk::<T, _, _>(raw, header, |mut checker, allocator, _| {
+            allocator.check(&mut checker);
+            checker.finish(version)
+        })
+    }
+
+    /// Walks the pages of the object at `header`: its root file, then the
+    /// containers of each top-level field, which are only visited if the root
+    /// file is consistent. `finish` receives the checker along with the number
+    /// of pages referenced by each field.
+    fn walk<T: Object, F, R>(raw: RawDatabase, header: FileHeader, finish: F) -> io::Result<R>
+    where
+        F: FnOnce(Checker, &AllocatorState, Vec<(&'static str, usize)>) -> R,
+    {
+        raw.registry().track();
+        let allocator = *raw.allocator_state();
+        let pages = raw.pages();
+        let database = DatabaseRef::new(raw);
+        let lock = database.lock();
+        let mut checker = Checker::new(&lock, &allocator, pages);
+        checker.visit_pages(header.root, header.pages());
+        let mut fields = Vec::new();
+        if checker.is_consistent() {
+            let file = File::from_header(header, database.clone());
+            let content = T::deserialize(&mut file.read(), database.clone())?;
+            for (name, containers) in database.fields() {
+                let referenced = checker.referenced();
+                for container in containers {
+                    container.check(&mut checker);
+                }
+                fields.push((name, checker.referenced() - referenced));
+            }
+            drop(content);
+        }
+        Ok(finish(checker, &allocator, fields))
+    }
+
+    pub fn compact<T: Object>(path: &Path) -> io::Result<()> {
+        let writer_lock = lock_writer(path)?;
+        if !Self::check::<T>(path)?.is_consistent() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "cannot compact inconsistent database",
+            ));
+        }
+        let (raw, header) = Self::open_source::<T>(path)?;
+        Self::copy_to::<T>(raw, header, &writer_lock, path)?;
+        Ok(())
+    }
+
+    /// Copies the last snapshot of `path` to `destination`, even while `path`
+    /// is open for writing. Like a writer, it locks `<destination>.lock`.
+    pub fn backup<T: Object>(path: &Path, destination: &Path) -> io::Result<Backup> {
+        let (raw, header) = Self::open_source::<T>(path)?;
+        Self::copy_to::<T>(raw, header, &lock_writer(destination)?, destination)
+    }
+
+    /// Copies the last committed snapshot to `path`, locking `<path>.lock`
+    /// while it is written.
+    pub fn backup_to<T: Object>(&self, path: impl AsRef<Path>) -> io::Result<Backup> {
+        let path = path.as_ref();
+        let header = self.committed.ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, "database has no committed snapshot")
+        })?;
+        Self::copy_to::<T>(self.database.reader()?, header, &lock_writer(path)?, path)
+    }
+
+    fn open_source<T: Object>(path: &Path) -> io::Result<(RawDatabase, FileHeader)> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        lock_reader(&file)?;
+        RawDatabase::open_read_only(file, &T::format())
+    }
+
+    /// Copies the object at `header` into a fresh database that atomically
+    /// replaces `destination`. The copy is written next to it first, with the
+    /// "partial" extension.
+    fn copy_to<T: Object>(
+        source: RawDatabase,
+        header: FileHeader,
+        writer_lock: &fs::File,
+        destination: &Path,
+    ) -> io::Result<Backup> {
+        source.registry().track();
+        let checksums = source.checksums().is_some();
+        let source = DatabaseRef::new(source);
+        let temporary_path = destination.with_extension("partial");
+        let version = source.version();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&temporary_path)?;
+        file.set_len(0)?;
+        let target = DatabaseRef::new(RawDatabase::create(
+            file,
+            writer_lock.try_clone()?,
+            &T::format(),
+            checksums,
+        )?);
+        let root = {
+            let source_lock = source.lock();
+            let target_lock = target.lock();
+            let mut copier = Copier::new(&source_lock, &target_lock);
+            let root = copier.copy_pages(header.root, header.pages());
+            let file = File::from_header(header, source.clone());
+            let content = T::deserialize(&mut file.read(), source.clone())?;
+            for container in source.containers() {
+                target.relocate(container.root(), container.copy(&mut copier));
+            }
+            drop(content);
+            root
+        };
+        drop(source);
+        let header = FileHeader {
+            root,
+            len: header.len,
+        };
+        let file = File::from_header(header, target.clone());
+        let mut content = T::deserialize(&mut file.read(), target.clone())?;
+        let mut database = Self {
+            file,
+            database: target,
+            committed: None,
+            retention: MAX_RETAINED,
+        };
+        database.snapshot(&mut content)?;
+        let pages = database.database.last_page() as usize + 1;
+        let len = (pages * PAGE_SIZE) as u64;
+        drop(database);
+        drop(content);
+        let file = OpenOptions::new().write(true).open(&temporary_path)?;
+        file.set_len(len)?;
+        file.sync_all()?;
+        rename(temporary_path, destination)?;
+        Ok(Backup {
+            version,
+            pages,
+            bytes: len,
+        })
     }
 
+    /// Creates a new database at `path`. As with [`Database::open`], the writer
+    /// lock lives in a `<path>.lock` file that outlasts the database handle.
     pub fn create<T: Object>(
         path: impl AsRef<Path>,
         constructor: impl FnOnce(DatabaseRef) -> T,
     ) -> io::Result<(Self, T)> {
+        Self::create_with(path, false, constructor)
+    }
+
+    pub fn create_with_checksums<T: Object>(
+        path: impl AsRef<Path>,
+        constructor: impl FnOnce(DatabaseRef) -> T,
+    ) -> io::Result<(Self, T)> {
+        Self::create_with(path, true, constructor)
+    }
+
+    fn create_with<T: Object>(
+        path: impl AsRef<Path>,
+        checksums: bool,
+        constructor: impl FnOnce(DatabaseRef) -> T,
+    ) -> io::Result<(Self, T)> {
+        let path = path.as_ref();
+        let writer_lock = lock_writer(path)?;
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create_new(true)
             .open(path)?;
-        let raw = RawDatabase::create(file, &T::format())?;
+        let raw = RawDatabase::create(file, writer_lock, &T::format(), checksums)?;
+        Ok(Self::create_from(raw, constructor))
+    }
+
+    pub fn in_memory<T: Object>(
+        constructor: impl FnOnce(DatabaseRef) -> T,
+    ) -> io::Result<(Self, T)> {
+        let raw = RawDatabase::in_memory(&T::format())?;
+        Ok(Self::create_from(raw, constructor))
+    }
+
+    pub(crate) fn create_from<T: Object>(
+        raw: RawDatabase,
+        constructor: impl FnOnce(DatabaseRef) -> T,
+    ) -> (Self, T) {
         let database = DatabaseRef::new(raw);
         let file = File::new(database.clone());
         let content = constructor(database.clone());
-        Ok((Self { file, database }, content))
+        (
+            Self {
+                file,
+                database,
+                committed: None,
+                retention: MAX_RETAINED,
+            },
+            content,
+        )
+    }
+
+    pub fn set_verify(&self, verify: bool) {
+        self.database.set_verify(verify)
+    }
+
+    pub fn set_retention(&mut self, snapshots: usize) {
+        self.retention = snapshots.min(MAX_RETAINED);
     }
 
     pub fn snapshot<T: Object>(&mut self, content: &mut T) -> io::Result<()> {
+        self.commit(content, false)
+    }
+
+    pub fn checkpoint<T: Object>(&mut self, content: &mut T) -> io::Result<u64> {
+        let snapshots = self.list_snapshots();
+        let excess = (snapshots.len() + 1).saturating_sub(self.retention);
+        for version in snapshots.into_iter().take(excess) {
+            self.release_snapshot(version)?;
+        }
+        self.commit(content, self.retention > 0)?;
+        Ok(self.database.version())
+    }
+
+    fn commit<T: Object>(&mut self, content: &mut T, retain: bool) -> io::Result<()> {
         let mut writer = self.file.write();
         content.serialize(&mut writer)?;
         let size = writer.seek(SeekFrom::Current(0))?;
         writer.set_len(size);
         drop(writer);
-        self.database.snapshot(self.file.header())
+        self.database.snapshot(self.file.header(), retain)?;
+        self.committed = Some(self.file.header());
+        Ok(())
+    }
+
+    pub fn list_snapshots(&self) -> Vec<u64> {
+        self.database
+            .snapshots()
+            .iter()
+            .map(|snapshot| snapshot.version)
+            .collect()
+    }
+
+    pub fn release_snapshot(&mut self, version: u64) -> io::Result<()> {
+        let index = Self::find_snapshot(&self.database.snapshots(), version)?;
+        self.database.lock().release(index);
+        Ok(())
+    }
+
+    pub fn open_at<T: Object>(&self, version: u64) -> io::Result<ReadOnly<T>> {
+        let snapshots = self.database.snapshots();
+        let index = Self::find_snapshot(&snapshots, version)?;
+        let header = Self::retained_header::<T>(&snapshots[index])?;
+        let database = DatabaseRef::new(self.database.reader()?);
+        let file = File::from_header(header, database.clone());
+        let content = T::deserialize(&mut file.read(), database)?;
+        Ok(ReadOnly(content))
+    }
+
+    /// Replaces `content` with the retained snapshot `version`. On error the
+    /// database and `content` are left untouched.
+    pub fn restore<T: Object>(&mut self, content: &mut T, version: u64) -> io::Result<()> {
+        let (raw, _) = self.database.rollback(&T::format())?;
+        let database = DatabaseRef::new(raw);
+        let snapshots = database.snapshots();
+        let index = Self::find_snapshot(&snapshots, version)?;
+        let header = Self::retained_header::<T>(&snapshots[index])?;
+        let used = Self::used_pages::<T>(&database, header)?;
+        let (file, restored) = Self::load::<T>(&database, header)?;
+        database.restore(index, &used);
+        self.install(database, file, content, restored);
+        Ok(())
+    }
+
+    fn find_snapshot(snapshots: &[RetainedSnapshot], version: u64) -> io::Result<usize> {
+        snapshots
+            .iter()
+            .position(|snapshot| snapshot.version == version)
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("snapshot {} is not retained", version),
+                )
+            })
+    }
+
+    fn retained_header<T: Object>(snapshot: &RetainedSnapshot) -> io::Result<FileHeader> {
+        let format_version = T::format().version;
+        if snapshot.format_version != format_version {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "snapshot {} has database schema version {}, expected {}",
+                    snapshot.version, snapshot.format_version, format_version
+                ),
+            ));
+        }
+        Ok(FileHeader {
+            root: snapshot.root_nr,
+            len: snapshot.root_len,
+        })
+    }
+
+    fn used_pages<T: Object>(database: &DatabaseRef, header: FileHeader) -> io::Result<Vec<bool>> {
+        Self::walk::<T, _, _>(database.reader()?, header, |checker, _, _| {
+            checker.is_consistent().then(|| checker.into_used())
+        })?
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "cannot restore inconsistent snapshot",
+            )
+        })
+    }
+
+    /// Replaces `content` with the last committed snapshot. On error the
+    /// database and `content` are left untouched.
+    pub fn rollback<T: Object>(&mut self, content: &mut T) -> io::Result<()> {
+        if self.committed.is_none() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                "database has no committed snapshot",
+            ));
+        }
+        let (raw, header) = self.database.rollback(&T::format())?;
+        let database = DatabaseRef::new(raw);
+        let (file, rolled_back) = Self::load::<T>(&database, header)?;
+        self.install(database, file, content, rolled_back);
+        Ok(())
+    }
+
+    /// Deserializes `header` from a database that is not in use yet. It stays
+    /// closed until the content is loaded, so a failure frees nothing.
+    fn load<T: Object>(database: &DatabaseRef, header: FileHeader) -> io::Result<(File, T)> {
+        database.lock().close();
+        let file = File::from_header(header, database.clone());
+        let content = T::deserialize(&mut file.read(), database.clone())?;
+        database.lock().reopen();
+        Ok((file, content))
+    }
+
+    fn install<T: Object>(&mut self, database: DatabaseRef, file: File, content: &mut T, new: T) {
+        self.database.lock().close();
+        drop(replace(content, new));
+        self.database = database;
+        self.file = file;
+    }
+
+    pub fn stats<T: Object>(&self) -> io::Result<Stats> {
+        let allocator = self.database.allocator_state();
+        let (previous_free, current_free) = allocator.free_lists();
+        let mut stats = Stats {
+            version: self.database.version(),
+            timestamp: self.database.timestamp(),
+            format_version: self.database.format_version() as u64,
+            pages: PageStats {
+                total: self.database.pages() as u64,
+                live: 0,
+                last_page: allocator.last_page() as u64,
+                previous_free: previous_free.len() as u64,
+                current_free: current_free.len() as u64,
+                retained_snapshots: allocator.retained().len() as u64,
+            },
+            fields: FieldStats::default(),
+        };
+        let header = match self.committed {
+            Some(header) => header,
+            None => return Ok(stats),
+        };
+        let (live, fields) =
+            Self::walk::<T, _, _>(self.database.reader()?, header, |checker, _, fields| {
+                (checker.referenced(), fields)
+            })?;
+        stats.pages.live = live as u64;
+        for (name, pages) in fields {
+            let pages = pages as u64;
+            stats.fields.0.push((
+                name,
+                ContainerStats {
+                    pages,
+                    bytes: pages * PAGE_SIZE as u64,
+                },
+            ));
+        }
+        Ok(stats)
+    }
+
+    pub fn begin_read<T: Object>(&self) -> io::Result<ReadOnly<T>> {
+        let header = self.committed.ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, "database has no committed snapshot")
+        })?;
+        let database = DatabaseRef::new(self.database.reader()?);
+        let file = File::from_header(header, database.clone());
+        let content = T::deserialize(&mut file.read(), database)?;
+        Ok(ReadOnly(content))
     }
 }
 
 impl Drop for Database {
     fn drop(&mut self) {
-        self.database.lock().close()
+        self.database.lock().close();
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Backup {
+    pub version: u64,
+    pub pages: usize,
+    pub bytes: u64,
+}
+
+pub struct ReadOnly<T>(T);
+
+impl<T> Deref for ReadOnly<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
 }
@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    database::Database, mmap::MappedFile, object::Object, page::PAGE_SIZE, raw::RawDatabase,
+    reference::DatabaseRef,
+};
+
+const SECTOR_SIZE: usize = 512;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Faults {
+    pub lost_write: f64,
+    pub torn_write: f64,
+    pub failed_sync: f64,
+    pub crash_during_sync: f64,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            lost_write: 0.5,
+            torn_write: 0.25,
+            failed_sync: 0.02,
+            crash_during_sync: 0.05,
+        }
+    }
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+/// Temporary storage that models which writes reached the disk, for crash
+/// consistency tests.
+pub struct FaultyStorage {
+    data: MappedFile,
+}
+
+impl FaultyStorage {
+    pub fn new(faults: Faults, seed: u64) -> io::Result<Self> {
+        let faults = FaultInjector::new(faults, seed);
+        let data = MappedFile::with_faults(tempfile::tempfile()?, faults)?;
+        Ok(Self { data })
+    }
+
+    pub fn create<T: Object>(
+        &self,
+        checksums: bool,
+        constructor: impl FnOnce(DatabaseRef) -> T,
+    ) -> io::Result<(Database, T)> {
+        let raw = RawDatabase::create_mapped(self.data.clone(), &T::format(), checksums)?;
+        Ok(Database::create_from(raw, constructor))
+    }
+
+    /// Returns the file contents a crash right now would leave behind.
+    pub fn crash(&self) -> Vec<u8> {
+        self.data.crash()
+    }
+}
+
+pub(crate) struct FaultInjector(Mutex<Disk>);
+
+struct Disk {
+    faults: Faults,
+    rng: Rng,
+    durable: Vec<u8>,
+    dropped: HashMap<usize, Vec<u8>>,
+    crashed: Option<Vec<u8>>,
+}
+
+impl FaultInjector {
+    fn new(faults: Faults, seed: u64) -> Arc<Self> {
+        Arc::new(Self(Mutex::new(Disk {
+            faults,
+            rng: Rng::new(seed),
+            durable: Vec::new(),
+            dropped: HashMap::new(),
+            crashed: None,
+        })))
+    }
+
+    pub(crate) fn sync(&self, memory: &[u8]) -> io::Result<()> {
+        let mut disk = self.0.lock().unwrap();
+        let disk = &mut *disk;
+        if disk.crashed.is_some() {
+            return Err(io::Error::new(ErrorKind::Other, "injected crash"));
+        }
+        if disk.rng.chance(disk.faults.crash_during_sync) {
+            disk.crashed = Some(disk.image(memory));
+            return Err(io::Error::new(ErrorKind::Other, "injected crash"));
+        }
+        let failed = disk.rng.chance(disk.faults.failed_sync);
+        for page in disk.dirty(memory) {
+            if failed && disk.rng.chance(0.5) {
+                let range = page_range(page, memory.len());
+                disk.dropped.insert(page, memory[range].to_vec());
+            } else {
+                disk.dropped.remove(&page);
+                write_page(&mut disk.durable, memory, page, PAGE_SIZE);
+            }
+        }
+        if failed {
+            return Err(io::Error::new(ErrorKind::Other, "injected sync failure"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn crash(&self, memory: &[u8]) -> Vec<u8> {
+        let mut disk = self.0.lock().unwrap();
+        match disk.crashed.take() {
+            Some(image) => image,
+            None => disk.image(memory),
+        }
+    }
+}
+
+impl Disk {
+    fn dirty(&self, memory: &[u8]) -> Vec<usize> {
+        (0..(memory.len() + PAGE_SIZE - 1) / PAGE_SIZE)
+            .filter(|page| {
+                let range = page_range(*page, memory.len());
+                let content = &memory[range.clone()];
+                if let Some(dropped) = self.dropped.get(page) {
+                    if dropped.as_slice() == content {
+                        return false;
+                    }
+                }
+                self.durable
+                    .get(range)
+                    .map_or(true, |durable| durable != content)
+            })
+            .collect()
+    }
+
+    fn image(&mut self, memory: &[u8]) -> Vec<u8> {
+        let mut image = self.durable.clone();
+        for page in self.dirty(memory) {
+            if self.rng.chance(self.faults.lost_write) {
+                continue;
+            }
+            let len = if self.rng.chance(self.faults.torn_write) {
+                SECTOR_SIZE * (1 + self.rng.below(PAGE_SIZE / SECTOR_SIZE - 1))
+            } else {
+                PAGE_SIZE
+            };
+            write_page(&mut image, memory, page, len);
+        }
+        image
+    }
+}
+
+fn page_range(page: usize, len: usize) -> Range<usize> {
+    page * PAGE_SIZE..((page + 1) * PAGE_SIZE).min(len)
+}
+
+fn write_page(image: &mut Vec<u8>, memory: &[u8], page: usize, len: usize) {
+    let range = page_range(page, memory.len());
+    if image.len() < range.end {
+        image.resize(range.end, 0);
+    }
+    let end = (range.start + len).min(range.end);
+    image[range.start..end].copy_from_slice(&memory[range.start..end]);
+}
@@ -4,6 +4,9 @@ use std::{
 };
 
 use crate::{
+    check::Checker,
+    compact::Copier,
+    container::{Container, Walk},
     cursor::{reallocate, PageLookup},
     lock::Lock,
     page::{PageNr, PAGE_SIZE},
@@ -18,7 +21,7 @@ pub struct FileHeader {
 }
 
 impl FileHeader {
-    fn pages(&self) -> usize {
+    pub fn pages(&self) -> usize {
         (self.len as usize + PAGE_SIZE - 1) / PAGE_SIZE
     }
 }
@@ -43,10 +46,11 @@ impl File {
     pub fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
         let mut bytes = [0; 4];
         reader.read_exact(&mut bytes)?;
-        let root = u32::from_ne_bytes(bytes);
+        let root = u32::from_le_bytes(bytes);
         let mut bytes = [0; 8];
         reader.read_exact(&mut bytes)?;
-        let len = u64::from_ne_bytes(bytes);
+        let len = u64::from_le_bytes(bytes);
+        let root = database.register(Container::new::<Self>(root, len));
         Ok(Self {
             header: FileHeader { root, len },
             database,
@@ -54,8 +58,8 @@ impl File {
     }
 
     pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
-        writer.write_all(&self.header.root.to_ne_bytes())?;
-        writer.write_all(&self.header.len.to_ne_bytes())?;
+        writer.write_all(&self.header.root.to_le_bytes())?;
+        writer.write_all(&self.header.len.to_le_bytes())?;
         Ok(())
     }
 
@@ -114,9 +118,9 @@ impl<'a, H: Deref<Target = FileHeader>> Read for FileGuard<'a, H> {
             let offset = self.pos as usize % PAGE_SIZE;
             let n = buf.len().min(PAGE_SIZE - offset);
             let (a, b) = buf.split_at_mut(n);
-            let page = self
-                .lookup
-                .get(self.header.root, self.header.pages(), index, &self.lock);
+            let page =
+                self.lookup
+                    .try_get(self.header.root, self.header.pages(), index, &self.lock)?;
             a.copy_from_slice(&page[offset..offset + n]);
             self.pos += n as u64;
             buf = b;
@@ -166,6 +170,16 @@ pub struct FileGuard<'a, H: Deref<Target = FileHeader>> {
 pub type ReadFileGuard<'a> = FileGuard<'a, &'a FileHeader>;
 pub type WriteFileGuard<'a> = FileGuard<'a, &'a mut FileHeader>;
 
+impl Walk for File {
+    fn check(checker: &mut Checker, root: PageNr, len: u64) {
+        checker.visit_pages(root, FileHeader { root, len }.pages())
+    }
+
+    fn copy(copier: &mut Copier, root: PageNr, len: u64) -> PageNr {
+        copier.copy_pages(root, FileHeader { root, len }.pages())
+    }
+}
+
 impl Drop for File {
     fn drop(&mut self) {
         let lock = self.database.lock();
@@ -0,0 +1,46 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+
+/// Takes the writer lock of the database at `path`. It lives in a separate
+/// `.lock` file so that readers, which lock the database file itself, never
+/// contend with the writer. The `.lock` file is never removed, as deleting
+/// it could let a second writer lock a new file while the first still holds
+/// the old one.
+pub fn lock_writer(path: &Path) -> io::Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(PathBuf::from(lock_path))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(file),
+        Err(error) if error.kind() == fs2::lock_contended_error().kind() => Err(io::Error::new(
+            ErrorKind::WouldBlock,
+            "database is already opened for writing by another process",
+        )),
+        Err(error) => Err(error),
+    }
+}
+
+/// Registers a reader of the database file. The writer only holds the file
+/// lock for the instant it takes [`has_readers`] to check, so this waits.
+pub fn lock_reader(file: &File) -> io::Result<()> {
+    file.lock_shared()
+}
+
+/// Checks whether another process has the database file open for reading,
+/// in which case pages freed since its snapshot must not be recycled.
+pub fn has_readers(file: &File) -> io::Result<bool> {
+    match file.try_lock_exclusive() {
+        Ok(()) => file.unlock().map(|()| false),
+        Err(error) if error.kind() == fs2::lock_contended_error().kind() => Ok(true),
+        Err(error) => Err(error),
+    }
+}
@@ -3,7 +3,8 @@ use std::mem::size_of;
 use bytemuck::{cast_mut, cast_ref, Pod, Zeroable};
 
 use crate::{
-    allocator::Allocator,
+    allocator::{Allocator, Target},
+    check::{Checker, Problem},
     page::{Page, PageNr, Pager, NULL_PAGE_NR, PAGE_SIZE},
 };
 
@@ -34,6 +35,10 @@ impl FreeList {
         }
     }
 
+    pub fn len(&self) -> usize {
+        (self.back - self.front) as usize
+    }
+
     pub fn reset_front(&mut self) {
         self.front = 0;
     }
@@ -47,10 +52,37 @@ impl FreeList {
         }
     }
 
-    unsafe fn set(allocator: &mut Allocator, index: u32, value: PageNr) {
+    pub(crate) fn check(&self, checker: &mut Checker) {
+        if self.root != NULL_PAGE_NR {
+            self.check_page(self.root, 0, 0, checker)
+        } else if self.back > 0 {
+            checker.problem(Problem::Missing(NULL_PAGE_NR))
+        }
+    }
+
+    fn check_page(&self, page_nr: PageNr, offset: usize, depth: usize, checker: &mut Checker) {
+        if !checker.reference(page_nr) {
+            return;
+        }
+        let page = cast_ref::<Page, FreeListPage>(unsafe { checker.lock().page(page_nr) });
+        for (child_index, child) in page.iter().enumerate() {
+            let index = offset + child_index * ENTRIES_PER_DEPTH[depth];
+            if depth == MAX_DEPTH {
+                if index < self.back as usize {
+                    checker.free(*child, index < self.front as usize);
+                }
+            } else if *child != NULL_PAGE_NR {
+                self.check_page(*child, index, depth + 1, checker);
+            } else if index < self.back as usize {
+                checker.problem(Problem::Missing(page_nr));
+            }
+        }
+    }
+
+    unsafe fn set(allocator: &mut Allocator, target: Target, index: u32, value: PageNr) {
         let index = index as usize;
-        allocator.current_free().root = Self::set_inner(
-            allocator.current_free().root,
+        allocator.free_list(target).root = Self::set_inner(
+            allocator.free_list(target).root,
             index,
             value,
             0,
@@ -75,19 +107,57 @@ impl FreeList {
         }
     }
 
+    pub unsafe fn entries<'a>(&'a self, pager: &'a Pager) -> impl Iterator<Item = PageNr> + 'a {
+        (0..self.back).map(move |index| self.get(index, pager))
+    }
+
+    pub unsafe fn pages(&self, pager: &Pager) -> Vec<PageNr> {
+        let mut pages = Vec::new();
+        if self.root != NULL_PAGE_NR {
+            Self::collect_pages(self.root, 0, pager, &mut pages);
+        }
+        pages
+    }
+
+    unsafe fn collect_pages(page_nr: PageNr, depth: usize, pager: &Pager, pages: &mut Vec<PageNr>) {
+        pages.push(page_nr);
+        if depth < MAX_DEPTH {
+            for child in cast_ref::<Page, FreeListPage>(pager.page(page_nr)).iter() {
+                if *child != NULL_PAGE_NR {
+                    Self::collect_pages(*child, depth + 1, pager, pages);
+                }
+            }
+        }
+    }
+
     pub unsafe fn prepend(allocator: &mut Allocator, nrs: Vec<PageNr>) {
-        let mut index = allocator.current_free().front - nrs.len() as u32;
+        let mut index = allocator.free_list(Target::Current).front - nrs.len() as u32;
         for nr in nrs {
-            Self::set(allocator, index, nr);
+            Self::set(allocator, Target::Current, index, nr);
             index += 1;
         }
     }
 
     pub unsafe fn append(allocator: &mut Allocator, nrs: Vec<PageNr>) {
         for nr in nrs {
-            let index = allocator.current_free().back;
-            Self::set(allocator, index, nr);
-            allocator.current_free().back += 1;
+            let mut index = allocator.free_list(Target::Current).back;
+            Self::set(allocator, Target::Current, index, nr);
+            while allocator.free_list(Target::Current).back != index {
+                index = allocator.free_list(Target::Current).back;
+                Self::set(allocator, Target::Current, index, nr);
+            }
+            allocator.free_list(Target::Current).back += 1;
+        }
+    }
+
+    pub unsafe fn park(allocator: &mut Allocator, nrs: Vec<(usize, PageNr)>) {
+        for (index, nr) in nrs {
+            let target = Target::Dead(index);
+            let back = allocator.free_list(target).back;
+            Self::set(allocator, target, back, nr);
+            let list = allocator.free_list(target);
+            list.back += 1;
+            list.front = list.back;
         }
     }
 
@@ -0,0 +1,96 @@
+use std::{
+    marker::PhantomData,
+    mem::{align_of, size_of},
+};
+
+use bytemuck::{cast_slice, cast_slice_mut, Pod, TransparentWrapper};
+
+use crate::page::{Page, PageNr, PAGE_SIZE};
+
+const FOOTER_SIZE: usize = 8;
+
+#[derive(Clone, Copy, TransparentWrapper)]
+#[repr(transparent)]
+#[transparent(Page)]
+pub struct Bucket<K, V> {
+    page: Page,
+    _phantom_key: PhantomData<K>,
+    _phantom_value: PhantomData<V>,
+}
+
+impl<K, V> Bucket<K, V> {
+    pub const fn capacity() -> usize {
+        (PAGE_SIZE - FOOTER_SIZE - align_of::<V>()) / (size_of::<K>() + size_of::<V>())
+    }
+
+    const fn value_offset() -> usize {
+        let value_align = align_of::<V>();
+        let keys_size = Self::capacity() * size_of::<K>();
+        (keys_size + value_align - 1) / value_align * value_align
+    }
+}
+
+impl<K: Pod, V: Pod> Bucket<K, V> {
+    fn footer(&self) -> &[u32] {
+        cast_slice(&self.page[PAGE_SIZE - FOOTER_SIZE..])
+    }
+
+    fn footer_mut(&mut self) -> &mut [u32] {
+        cast_slice_mut(&mut self.page[PAGE_SIZE - FOOTER_SIZE..])
+    }
+
+    pub fn len(&self) -> usize {
+        self.footer()[0] as usize
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        self.footer_mut()[0] = len as u32
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == Self::capacity()
+    }
+
+    pub fn overflow(&self) -> PageNr {
+        self.footer()[1]
+    }
+
+    pub fn overflow_mut(&mut self) -> &mut PageNr {
+        &mut self.footer_mut()[1]
+    }
+
+    pub fn keys(&self) -> &[K] {
+        cast_slice(&self.page[0..self.len() * size_of::<K>()])
+    }
+
+    pub fn keys_mut(&mut self) -> &mut [K] {
+        let len = self.len();
+        cast_slice_mut(&mut self.page[0..len * size_of::<K>()])
+    }
+
+    pub fn values(&self) -> &[V] {
+        let offset = Self::value_offset();
+        cast_slice(&self.page[offset..offset + self.len() * size_of::<V>()])
+    }
+
+    pub fn values_mut(&mut self) -> &mut [V] {
+        let offset = Self::value_offset();
+        let len = self.len();
+        cast_slice_mut(&mut self.page[offset..offset + len * size_of::<V>()])
+    }
+
+    pub fn push(&mut self, key: K, value: V) {
+        let index = self.len();
+        assert!(index < Self::capacity());
+        self.set_len(index + 1);
+        self.keys_mut()[index] = key;
+        self.values_mut()[index] = value;
+    }
+
+    pub fn pop(&mut self) -> (K, V) {
+        let index = self.len() - 1;
+        let entry = (self.keys()[index], self.values()[index]);
+        self.set_len(index);
+        entry
+    }
+}
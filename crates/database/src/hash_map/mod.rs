@@ -0,0 +1,447 @@
+mod bucket;
+mod hasher;
+
+use std::{
+    hash::Hash,
+    io::{self, Read, Write},
+    marker::PhantomData,
+    mem::replace,
+    ops::{Deref, DerefMut},
+};
+
+use bytemuck::{Pod, TransparentWrapper};
+
+use crate::{
+    check::{Checker, Problem},
+    compact::Copier,
+    container::{Container, Walk},
+    cursor::{reallocate, PageLookup},
+    lock::Lock,
+    page::{PageNr, NULL_PAGE_NR},
+    reference::DatabaseRef,
+    vec::Len,
+};
+
+use self::{bucket::Bucket, hasher::StableHasher};
+
+#[derive(Clone, Default, Copy)]
+#[repr(C)]
+pub struct HashMapHeader {
+    root: PageNr,
+    buckets: u64,
+    len: u64,
+}
+
+pub struct HashMap<K: Pod + Hash + Eq, V: Pod> {
+    header: HashMapHeader,
+    database: DatabaseRef,
+    _phantom_key: PhantomData<K>,
+    _phantom_value: PhantomData<V>,
+}
+
+impl<K: Pod + Hash + Eq, V: Pod> HashMap<K, V> {
+    pub fn new(database: DatabaseRef) -> Self {
+        Self {
+            header: HashMapHeader::default(),
+            database,
+            _phantom_key: PhantomData,
+            _phantom_value: PhantomData,
+        }
+    }
+
+    pub fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes)?;
+        let root = u32::from_le_bytes(bytes);
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        let buckets = u64::from_le_bytes(bytes);
+        reader.read_exact(&mut bytes)?;
+        let len = u64::from_le_bytes(bytes);
+        let root = database.register(Container::new::<Self>(root, buckets));
+        Ok(Self {
+            header: HashMapHeader { root, buckets, len },
+            database,
+            _phantom_key: PhantomData,
+            _phantom_value: PhantomData,
+        })
+    }
+
+    pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.header.root.to_le_bytes())?;
+        writer.write_all(&self.header.buckets.to_le_bytes())?;
+        writer.write_all(&self.header.len.to_le_bytes())?;
+        Ok(())
+    }
+
+    pub fn read(&self) -> ReadHashMapGuard<'_, K, V> {
+        HashMapGuard {
+            header: &self.header,
+            lock: self.database.lock(),
+            _phantom_key: PhantomData,
+            _phantom_value: PhantomData,
+        }
+    }
+
+    pub fn write(&mut self) -> WriteHashMapGuard<'_, K, V> {
+        HashMapGuard {
+            header: &mut self.header,
+            lock: self.database.lock(),
+            _phantom_key: PhantomData,
+            _phantom_value: PhantomData,
+        }
+    }
+}
+
+impl<K: Pod + Hash + Eq, V: Pod> Walk for HashMap<K, V> {
+    fn check(checker: &mut Checker, root: PageNr, buckets: u64) {
+        let buckets = buckets as usize;
+        checker.visit_pages(root, buckets);
+        if !checker.is_consistent() {
+            return;
+        }
+        let lock = checker.lock();
+        let mut lookup = PageLookup::Invalid;
+        for index in 0..buckets {
+            let mut nr = root;
+            let mut bucket = Bucket::<K, V>::wrap_ref(lookup.get(root, buckets, index, lock));
+            loop {
+                if bucket.len() > Bucket::<K, V>::capacity() {
+                    checker.problem(Problem::Overfilled(nr));
+                    break;
+                }
+                nr = bucket.overflow();
+                if nr == NULL_PAGE_NR || !checker.reference(nr) {
+                    break;
+                }
+                bucket = Bucket::wrap_ref(unsafe { lock.page(nr) });
+            }
+        }
+    }
+
+    fn copy(copier: &mut Copier, root: PageNr, buckets: u64) -> PageNr {
+        let buckets = buckets as usize;
+        let mut root = copier.copy_pages(root, buckets);
+        let target = copier.target();
+        let mut lookup = PageLookup::Invalid;
+        for index in 0..buckets {
+            let page = unsafe {
+                lookup
+                    .get_mut(&mut root, buckets, index, target)
+                    .as_mut()
+                    .unwrap()
+            };
+            let mut overflow = Bucket::<K, V>::wrap_mut(page).overflow_mut();
+            while *overflow != NULL_PAGE_NR {
+                let (nr, page) = copier.copy_page(*overflow);
+                *overflow = nr;
+                overflow = Bucket::<K, V>::wrap_mut(page).overflow_mut();
+            }
+        }
+        root
+    }
+}
+
+impl<K: Pod + Hash + Eq, V: Pod> Drop for HashMap<K, V> {
+    fn drop(&mut self) {
+        let lock = self.database.lock();
+        if !lock.is_closing() {
+            drop(lock);
+            self.write().clear();
+        }
+    }
+}
+
+pub struct HashMapGuard<'a, K: Pod + Hash + Eq, V: Pod, H> {
+    header: H,
+    lock: Lock<'a>,
+    _phantom_key: PhantomData<K>,
+    _phantom_value: PhantomData<V>,
+}
+
+pub type ReadHashMapGuard<'a, K, V> = HashMapGuard<'a, K, V, &'a HashMapHeader>;
+pub type WriteHashMapGuard<'a, K, V> = HashMapGuard<'a, K, V, &'a mut HashMapHeader>;
+
+#[derive(Clone, Copy)]
+struct Location {
+    bucket: usize,
+    depth: usize,
+    index: usize,
+}
+
+impl<'a, K: Pod + Hash + Eq, V: Pod, H: Deref<Target = HashMapHeader>> HashMapGuard<'a, K, V, H> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let location = self.locate(key)?;
+        let bucket = self.bucket(location.bucket, location.depth);
+        Some(&bucket.values()[location.index])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.locate(key).is_some()
+    }
+
+    pub fn iter(&self) -> Iter<'_, 'a, K, V> {
+        Iter {
+            root: self.header.root,
+            buckets: self.header.buckets as usize,
+            next_bucket: 0,
+            current: None,
+            pos: 0,
+            lock: &self.lock,
+        }
+    }
+
+    fn bucket_index(&self, key: &K) -> usize {
+        let buckets = self.header.buckets;
+        let low = 1 << (63 - buckets.leading_zeros());
+        let hash = StableHasher::hash(key);
+        let index = hash & (low - 1);
+        if index < buckets - low {
+            (hash & (2 * low - 1)) as usize
+        } else {
+            index as usize
+        }
+    }
+
+    fn bucket(&self, index: usize, depth: usize) -> &Bucket<K, V> {
+        let pages = self.header.buckets as usize;
+        let page = PageLookup::Invalid.get(self.header.root, pages, index, &self.lock);
+        let mut bucket = Bucket::wrap_ref(page);
+        for _ in 0..depth {
+            bucket = Bucket::wrap_ref(unsafe { self.lock.page(bucket.overflow()) });
+        }
+        bucket
+    }
+
+    fn chain_len(&self, index: usize) -> usize {
+        let mut bucket = self.bucket(index, 0);
+        let mut depth = 1;
+        while bucket.overflow() != NULL_PAGE_NR {
+            bucket = Bucket::wrap_ref(unsafe { self.lock.page(bucket.overflow()) });
+            depth += 1;
+        }
+        depth
+    }
+
+    fn locate(&self, key: &K) -> Option<Location> {
+        if self.header.buckets == 0 {
+            return None;
+        }
+        let index = self.bucket_index(key);
+        let mut bucket = self.bucket(index, 0);
+        let mut depth = 0;
+        loop {
+            if let Some(position) = bucket.keys().iter().position(|k| k == key) {
+                return Some(Location {
+                    bucket: index,
+                    depth,
+                    index: position,
+                });
+            }
+            if bucket.overflow() == NULL_PAGE_NR {
+                return None;
+            }
+            bucket = Bucket::wrap_ref(unsafe { self.lock.page(bucket.overflow()) });
+            depth += 1;
+        }
+    }
+}
+
+impl<'a, K: Pod + Hash + Eq, V: Pod, H: Deref<Target = HashMapHeader>> Len
+    for HashMapGuard<'a, K, V, H>
+{
+    fn len(&self) -> usize {
+        self.header.len as usize
+    }
+}
+
+impl<'a, K: Pod + Hash + Eq, V: Pod, H: DerefMut<Target = HashMapHeader>>
+    HashMapGuard<'a, K, V, H>
+{
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let location = self.locate(key)?;
+        let bucket = self.bucket_mut(location.bucket, location.depth);
+        Some(&mut bucket.values_mut()[location.index])
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(location) = self.locate(&key) {
+            let bucket = self.bucket_mut(location.bucket, location.depth);
+            return Some(replace(&mut bucket.values_mut()[location.index], value));
+        }
+        if self.header.buckets == 0 {
+            reallocate(&mut self.header.root, 0, 1, &self.lock);
+            self.header.buckets = 1;
+        }
+        let index = self.bucket_index(&key);
+        self.push(index, key, value);
+        self.header.len += 1;
+        if self.header.len > self.header.buckets * Bucket::<K, V>::capacity() as u64 * 3 / 4 {
+            self.split();
+        }
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let location = self.locate(key)?;
+        let last = self.chain_len(location.bucket) - 1;
+        let bucket = self.bucket_mut(location.bucket, last);
+        let (last_key, last_value) = bucket.pop();
+        let value = if location.depth == last && location.index == bucket.len() {
+            last_value
+        } else {
+            let bucket = self.bucket_mut(location.bucket, location.depth);
+            bucket.keys_mut()[location.index] = last_key;
+            replace(&mut bucket.values_mut()[location.index], last_value)
+        };
+        if last > 0 && self.bucket(location.bucket, last).len() == 0 {
+            let parent = self.bucket_mut(location.bucket, last - 1);
+            let nr = replace(parent.overflow_mut(), NULL_PAGE_NR);
+            unsafe { self.lock.deallocate(nr) };
+        }
+        self.header.len -= 1;
+        if self.header.buckets > 1
+            && self.header.len < self.header.buckets * Bucket::<K, V>::capacity() as u64 / 4
+        {
+            self.merge();
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        let buckets = self.header.buckets as usize;
+        for index in 0..buckets {
+            self.free_overflow(index);
+        }
+        reallocate(&mut self.header.root, buckets, 0, &self.lock);
+        self.header.buckets = 0;
+        self.header.len = 0;
+    }
+
+    fn bucket_mut(&mut self, index: usize, depth: usize) -> &mut Bucket<K, V> {
+        let pages = self.header.buckets as usize;
+        let page = unsafe {
+            PageLookup::Invalid
+                .get_mut(&mut self.header.root, pages, index, &self.lock)
+                .as_mut()
+                .unwrap()
+        };
+        let mut bucket = Bucket::wrap_mut(page);
+        for _ in 0..depth {
+            bucket = Bucket::wrap_mut(unsafe { self.lock.page_mut(bucket.overflow_mut()) });
+        }
+        bucket
+    }
+
+    fn push(&mut self, index: usize, key: K, value: V) {
+        let last = self.chain_len(index) - 1;
+        let depth = if self.bucket(index, last).is_full() {
+            last + 1
+        } else {
+            last
+        };
+        self.bucket_mut(index, depth).push(key, value);
+    }
+
+    fn free_overflow(&mut self, index: usize) {
+        let mut bucket = self.bucket(index, 0);
+        let mut overflow = Vec::new();
+        while bucket.overflow() != NULL_PAGE_NR {
+            overflow.push(bucket.overflow());
+            bucket = Bucket::wrap_ref(unsafe { self.lock.page(bucket.overflow()) });
+        }
+        for nr in overflow {
+            unsafe { self.lock.deallocate(nr) };
+        }
+    }
+
+    fn drain(&mut self, index: usize) -> Vec<(K, V)> {
+        let mut entries = Vec::new();
+        let mut bucket = self.bucket(index, 0);
+        loop {
+            entries.extend(
+                bucket
+                    .keys()
+                    .iter()
+                    .copied()
+                    .zip(bucket.values().iter().copied()),
+            );
+            if bucket.overflow() == NULL_PAGE_NR {
+                break;
+            }
+            bucket = Bucket::wrap_ref(unsafe { self.lock.page(bucket.overflow()) });
+        }
+        self.free_overflow(index);
+        let bucket = self.bucket_mut(index, 0);
+        bucket.set_len(0);
+        *bucket.overflow_mut() = NULL_PAGE_NR;
+        entries
+    }
+
+    fn split(&mut self) {
+        let buckets = self.header.buckets as usize;
+        let low = 1 << (63 - self.header.buckets.leading_zeros());
+        let entries = self.drain(buckets - low);
+        reallocate(&mut self.header.root, buckets, buckets + 1, &self.lock);
+        self.header.buckets += 1;
+        for (key, value) in entries {
+            let index = self.bucket_index(&key);
+            self.push(index, key, value);
+        }
+    }
+
+    fn merge(&mut self) {
+        let buckets = self.header.buckets as usize;
+        let entries = self.drain(buckets - 1);
+        reallocate(&mut self.header.root, buckets, buckets - 1, &self.lock);
+        self.header.buckets -= 1;
+        for (key, value) in entries {
+            let index = self.bucket_index(&key);
+            self.push(index, key, value);
+        }
+    }
+}
+
+pub struct Iter<'a, 'b, K: Pod, V: Pod> {
+    root: PageNr,
+    buckets: usize,
+    next_bucket: usize,
+    current: Option<&'a Bucket<K, V>>,
+    pos: usize,
+    lock: &'a Lock<'b>,
+}
+
+impl<'a, 'b, K: Pod, V: Pod> Iterator for Iter<'a, 'b, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.current {
+                Some(bucket) if self.pos < bucket.len() => {
+                    let pos = self.pos;
+                    self.pos += 1;
+                    return Some((&bucket.keys()[pos], &bucket.values()[pos]));
+                }
+                Some(bucket) if bucket.overflow() != NULL_PAGE_NR => {
+                    let page = unsafe { self.lock.page(bucket.overflow()) };
+                    self.current = Some(Bucket::wrap_ref(page));
+                    self.pos = 0;
+                }
+                _ => {
+                    if self.next_bucket == self.buckets {
+                        return None;
+                    }
+                    let page = PageLookup::Invalid.get(
+                        self.root,
+                        self.buckets,
+                        self.next_bucket,
+                        self.lock,
+                    );
+                    self.current = Some(Bucket::wrap_ref(page));
+                    self.next_bucket += 1;
+                    self.pos = 0;
+                }
+            }
+        }
+    }
+}
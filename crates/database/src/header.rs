@@ -1,34 +1,121 @@
 use std::{
     convert::TryInto,
     io::{self, ErrorKind},
+    mem::size_of,
 };
 
-use bytemuck::{bytes_of, Pod, Zeroable};
+use bytemuck::{bytes_of, from_bytes, Pod, Zeroable};
 use sha3::{Digest, Sha3_512};
 
 use crate::{
     allocator::AllocatorState,
+    free_list::FreeList,
     page::{Page, PageNr},
 };
 
-pub type Format = [u8; 256];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Format {
+    pub application: &'static str,
+    pub version: u32,
+    pub legacy: Option<ApplicationId>,
+}
+
+impl Format {
+    pub const fn new(application: &'static str, version: u32) -> Self {
+        Self {
+            application,
+            version,
+            legacy: None,
+        }
+    }
+
+    /// Accepts files written before headers carried a layout version, whose
+    /// header starts with the given raw format id. They open as schema
+    /// version 0.
+    pub const fn with_legacy(self, id: ApplicationId) -> Self {
+        Self {
+            legacy: Some(id),
+            ..self
+        }
+    }
+
+    fn application_id(&self) -> ApplicationId {
+        let bytes = self.application.as_bytes();
+        assert!(bytes.len() <= APPLICATION_ID_LEN, "application id too long");
+        let mut id = [0; APPLICATION_ID_LEN];
+        id[..bytes.len()].copy_from_slice(bytes);
+        id
+    }
+}
+
+const APPLICATION_ID_LEN: usize = 256;
+
+const HEADER_LAYOUT: u64 = 1;
+
+/// Set in every file. Headers are encoded as little-endian, but pages of
+/// `Pod` values are mapped as they are stored, so the format only supports
+/// little-endian targets and the crate refuses to build on big-endian ones.
+/// The flag leaves room for a byte-swapping variant should that change.
+const LITTLE_ENDIAN: u64 = 1;
+
+const CHECKSUMS: u64 = 2;
+
+pub type ApplicationId = [u8; APPLICATION_ID_LEN];
 
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct Header {
-    format: Format,
+    application: ApplicationId,
+    layout: u64,
+    flags: u64,
     snapshots: [Snapshot; 2],
 }
 
 impl Header {
-    pub fn new(format: Format) -> Self {
+    pub fn new(format: &Format, checksums: bool) -> Self {
+        let state = State {
+            format_version: format.version,
+            ..State::default()
+        };
         Self {
-            format,
-            snapshots: [
-                Snapshot::new(State::default()),
-                Snapshot::new(State::default()),
-            ],
+            application: format.application_id(),
+            layout: HEADER_LAYOUT,
+            flags: if checksums {
+                LITTLE_ENDIAN | CHECKSUMS
+            } else {
+                LITTLE_ENDIAN
+            },
+            snapshots: [Snapshot::new(state), Snapshot::new(state)],
+        }
+    }
+
+    pub fn upgrade(page: &Page, format: &Format) -> io::Result<Option<Self>> {
+        let legacy = from_bytes::<LegacyHeader>(&page[..size_of::<LegacyHeader>()]);
+        if format.legacy != Some(legacy.format) {
+            return Ok(None);
         }
+        let state = legacy
+            .last_state()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupted database"))?;
+        let mut header = Self {
+            application: format.application_id(),
+            layout: HEADER_LAYOUT,
+            flags: LITTLE_ENDIAN,
+            snapshots: Zeroable::zeroed(),
+        };
+        header.snapshot(State::new(
+            state.version,
+            0,
+            AllocatorState::from_free_lists(
+                state.previous_free,
+                state.current_free,
+                state.last_page,
+            ),
+            state.root_nr,
+            state.root_len,
+            0,
+        ));
+        Ok(Some(header))
     }
 
     pub fn snapshot(&mut self, state: State) {
@@ -36,15 +123,55 @@ impl Header {
     }
 
     pub fn validate(&self, format: &Format) -> io::Result<State> {
-        if self.format != *format {
+        if self.application != format.application_id() {
             return Err(io::Error::new(
                 ErrorKind::InvalidData,
-                "database format mismatch",
+                format!(
+                    "database application mismatch: found {:?}, expected {:?}",
+                    self.application(),
+                    format.application
+                ),
             ));
         }
-        self.last_snapshot()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupted database"))
-            .map(|s| s.state)
+        if self.layout != HEADER_LAYOUT {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported database header layout {}", self.layout),
+            ));
+        }
+        if self.flags & LITTLE_ENDIAN == 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "database is not in little-endian format",
+            ));
+        }
+        let state = self
+            .last_snapshot()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupted database"))?
+            .state;
+        if state.format_version > format.version {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "database schema version {} is newer than supported version {}",
+                    state.format_version, format.version
+                ),
+            ));
+        }
+        Ok(state)
+    }
+
+    pub fn has_checksums(&self) -> bool {
+        self.flags & CHECKSUMS != 0
+    }
+
+    fn application(&self) -> String {
+        let len = self
+            .application
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(APPLICATION_ID_LEN);
+        String::from_utf8_lossy(&self.application[..len]).into_owned()
     }
 
     fn valid_snapshot(&self, index: usize) -> Option<&Snapshot> {
@@ -71,16 +198,13 @@ impl Header {
 #[repr(C)]
 pub struct HeaderPage {
     pub header: Header,
-    _padding9: [u8; 16],
-    _padding0: [u8; 32],
     _padding1: [u8; 16],
     _padding2: [u8; 32],
     _padding3: [u8; 64],
     _padding4: [u8; 128],
     _padding5: [u8; 256],
     _padding6: [u8; 1024],
-    _padding7: [u8; 2048],
-    _padding8: [u8; 4096],
+    _padding7: [u8; 4096],
 }
 
 assert_eq_size!(HeaderPage, Page);
@@ -89,18 +213,29 @@ assert_eq_size!(HeaderPage, Page);
 #[repr(C)]
 pub struct State {
     pub version: u64,
+    pub timestamp: u64,
     pub allocator: AllocatorState,
-    pub root_nr: PageNr,
     pub root_len: u64,
+    pub root_nr: PageNr,
+    pub format_version: u32,
 }
 
 impl State {
-    pub fn new(version: u64, allocator: AllocatorState, root_nr: PageNr, root_len: u64) -> Self {
+    pub fn new(
+        version: u64,
+        timestamp: u64,
+        allocator: AllocatorState,
+        root_nr: PageNr,
+        root_len: u64,
+        format_version: u32,
+    ) -> Self {
         Self {
             version,
+            timestamp,
             allocator,
-            root_nr,
             root_len,
+            root_nr,
+            format_version,
         }
     }
 
@@ -138,3 +273,53 @@ impl Snapshot {
 }
 
 pub type Checksum = [u8; 64];
+
+/// Header of files written before the header layout was versioned: a raw
+/// format id followed by two snapshots without timestamps, retention or
+/// schema version.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct LegacyHeader {
+    format: ApplicationId,
+    snapshots: [LegacySnapshot; 2],
+}
+
+impl LegacyHeader {
+    fn last_state(&self) -> Option<LegacyState> {
+        self.snapshots
+            .iter()
+            .enumerate()
+            .filter(|(index, snapshot)| {
+                snapshot.state.version as usize % 2 == index % 2
+                    && snapshot.state.checksum() == snapshot.checksum
+            })
+            .map(|(_, snapshot)| snapshot.state)
+            .max_by_key(|state| state.version)
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct LegacySnapshot {
+    state: LegacyState,
+    checksum: Checksum,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct LegacyState {
+    version: u64,
+    previous_free: FreeList,
+    current_free: FreeList,
+    last_page: PageNr,
+    root_nr: PageNr,
+    root_len: u64,
+}
+
+impl LegacyState {
+    fn checksum(&self) -> Checksum {
+        let mut hasher = Sha3_512::new();
+        hasher.update(bytes_of(self));
+        hasher.finalize()[..].try_into().unwrap()
+    }
+}
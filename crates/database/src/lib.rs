@@ -1,28 +1,54 @@
 mod allocator;
+mod blob;
+mod check;
+mod checksum;
+mod compact;
+mod container;
 mod cursor;
 mod database;
+#[cfg(feature = "fault-injection")]
+mod fault;
 mod file;
+mod flock;
 mod free_list;
+mod hash_map;
 mod header;
 mod lock;
-mod mapping;
+mod migration;
 mod mmap;
 mod object;
+mod one_to_many;
 mod page;
+mod pin;
 mod raw;
 mod reference;
-mod sync;
+mod retention;
+mod serialize;
+mod stats;
 mod tree;
 mod vec;
 
 #[macro_use]
 extern crate static_assertions;
 
-pub use crate::database::Database;
+#[cfg(not(target_endian = "little"))]
+compile_error!("the database file format requires a little-endian target");
+
+pub use crate::database::{Backup, Database, ReadOnly};
+pub use blob::{BlobTree, ReadBlobTreeGuard, WriteBlobTreeGuard, MAX_BLOB_KEY_LEN};
+pub use check::{CheckReport, Problem};
+#[cfg(feature = "fault-injection")]
+pub use fault::{Faults, FaultyStorage};
 pub use file::File;
+pub use hash_map::{HashMap, ReadHashMapGuard, WriteHashMapGuard};
 pub use header::Format;
-pub use mapping::*;
+pub use migration::{Migration, Migrations};
 pub use object::Object;
+pub use one_to_many::OneToMany;
 pub use reference::DatabaseRef;
-pub use tree::{Entry, Tree};
+pub use serialize::{
+    deserialize_value, layout, read_layout, serialize_value, write_layout, DbSerialize,
+};
+pub use stats::{ContainerStats, FieldStats, PageStats, Stats};
+pub use tree::{Entry, Iter, OccupiedEntry, ReadTreeGuard, Tree, VacantEntry, WriteTreeGuard};
 pub use vec::{Len, ReadVecGuard, Vec, WriteVecGuard};
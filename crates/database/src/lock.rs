@@ -1,5 +1,8 @@
+use std::io;
+
 use crate::{
     allocator::Allocator,
+    checksum::Checksums,
     page::{Page, PageNr, Pager, NULL_PAGE_NR},
     raw::RawDatabase,
 };
@@ -21,10 +24,25 @@ impl<'a> Lock<'a> {
         self.allocator().allocate()
     }
 
+    pub fn has_checksums(&self) -> bool {
+        self.database.checksums().is_some()
+    }
+
+    pub fn matches_checksum(&self, nr: PageNr) -> bool {
+        match self.database.checksums() {
+            Some(_) => unsafe { Checksums::matches(nr, &self.pager) },
+            None => true,
+        }
+    }
+
     pub fn close(&self) {
         self.database.close()
     }
 
+    pub fn reopen(&self) {
+        self.database.reopen()
+    }
+
     pub fn is_closing(&self) -> bool {
         self.database.is_closing()
     }
@@ -37,9 +55,25 @@ impl<'a> Lock<'a> {
         self.allocator().reallocate(nr)
     }
 
+    pub fn release(&self, index: usize) {
+        unsafe { self.allocator().release(index) }
+    }
+
     pub unsafe fn page(&self, nr: PageNr) -> &Page {
+        match self.try_page(nr) {
+            Ok(page) => page,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub unsafe fn try_page(&self, nr: PageNr) -> io::Result<&Page> {
         assert_ne!(nr, NULL_PAGE_NR);
-        self.pager.page(nr)
+        if let Some(checksums) = self.database.checksums() {
+            if !self.pager.can_write(nr) {
+                checksums.read(nr, &self.pager)?;
+            }
+        }
+        Ok(self.pager.page(nr))
     }
 
     pub unsafe fn page_mut(&self, nr: &mut PageNr) -> &mut Page {
@@ -71,7 +105,12 @@ impl<'a> Lock<'a> {
     }
 
     fn allocator(&self) -> Allocator<'_> {
-        Allocator::new(self.database.allocator_state(), &self.pager)
+        Allocator::new(
+            self.database.allocator_state(),
+            self.database.retention(),
+            &self.pager,
+            self.database.checksums(),
+        )
     }
 }
 
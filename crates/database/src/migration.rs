@@ -0,0 +1,39 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+};
+
+use crate::reference::DatabaseRef;
+
+pub type Migration = fn(&mut dyn Read, &mut dyn Write, DatabaseRef) -> io::Result<()>;
+
+#[derive(Default)]
+pub struct Migrations(HashMap<u32, Migration>);
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, from_version: u32, migration: Migration) -> Self {
+        assert!(
+            self.0.insert(from_version, migration).is_none(),
+            "duplicate migration from version {}",
+            from_version
+        );
+        self
+    }
+
+    pub(crate) fn get(&self, from_version: u32) -> io::Result<Migration> {
+        self.0.get(&from_version).copied().ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "no migration from database schema version {} to {}",
+                    from_version,
+                    from_version + 1
+                ),
+            )
+        })
+    }
+}
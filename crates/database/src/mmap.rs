@@ -9,43 +9,208 @@ use std::{
 };
 
 use bytemuck::Pod;
-use memmap2::MmapRaw;
+use memmap2::{Mmap, MmapMut, MmapRaw};
+
+#[cfg(feature = "fault-injection")]
+use crate::fault::FaultInjector;
+use crate::flock;
+
+pub enum Mapping {
+    ReadWrite(MmapRaw),
+    ReadOnly(Mmap),
+    /// Regions of an in-memory database. Growing maps another region instead
+    /// of moving the existing ones, so pages handed out before stay valid.
+    Anonymous(Vec<Arc<MmapMut>>),
+}
+
+impl Mapping {
+    fn new(file: &File, writable: bool) -> io::Result<Self> {
+        Ok(if writable {
+            Self::ReadWrite(MmapRaw::map_raw(file)?)
+        } else {
+            Self::ReadOnly(unsafe { Mmap::map(file)? })
+        })
+    }
+
+    fn grow_anonymous(&self, len: usize) -> io::Result<Self> {
+        let mut regions = match self {
+            Self::Anonymous(regions) => regions.clone(),
+            _ => unreachable!(),
+        };
+        regions.push(Arc::new(MmapMut::map_anon(len - self.len())?));
+        Ok(Self::Anonymous(regions))
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::ReadWrite(raw) => raw.len(),
+            Self::ReadOnly(map) => map.len(),
+            Self::Anonymous(regions) => regions.iter().map(|region| region.len()).sum(),
+        }
+    }
+
+    fn ptr(&self, mut offset: usize, len: usize) -> *const u8 {
+        match self {
+            Self::ReadWrite(raw) => unsafe { raw.as_ptr().add(offset) },
+            Self::ReadOnly(map) => unsafe { map.as_ptr().add(offset) },
+            Self::Anonymous(regions) => {
+                for region in regions {
+                    if offset < region.len() {
+                        assert!(offset + len <= region.len());
+                        return unsafe { region.as_ptr().add(offset) };
+                    }
+                    offset -= region.len();
+                }
+                panic!("offset out of range")
+            }
+        }
+    }
+
+    #[cfg(feature = "fault-injection")]
+    unsafe fn as_slice(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.ptr(0, self.len()), self.len())
+    }
+}
+
+struct MappedFileInner {
+    raw: Mutex<Arc<Mapping>>,
+    file: Option<File>,
+    writable: bool,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<Arc<FaultInjector>>,
+    /// Shared with read transactions through the mapping, so the writer lock
+    /// outlives the database until the last of them is gone.
+    _writer_lock: Option<File>,
+}
 
 #[derive(Clone)]
-pub struct MappedFile(Arc<(Mutex<Arc<MmapRaw>>, File)>);
+pub struct MappedFile(Arc<MappedFileInner>);
 
 impl MappedFile {
-    pub fn new(file: File) -> io::Result<Self> {
+    pub fn locked(file: File, writer_lock: File) -> io::Result<Self> {
+        Self::writable(file, Some(writer_lock))
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub(crate) fn with_faults(file: File, faults: Arc<FaultInjector>) -> io::Result<Self> {
+        let mut data = Self::writable(file, None)?;
+        Arc::get_mut(&mut data.0).unwrap().faults = Some(faults);
+        Ok(data)
+    }
+
+    fn writable(file: File, writer_lock: Option<File>) -> io::Result<Self> {
         let page_size = page_size::get() as u64;
         if file.metadata()?.len() < page_size {
             file.set_len(page_size)?;
         }
-        let raw = Arc::new(MmapRaw::map_raw(&file)?);
-        Ok(Self(Arc::new((Mutex::new(raw), file))))
+        let raw = Arc::new(Mapping::new(&file, true)?);
+        Ok(Self(Arc::new(MappedFileInner {
+            raw: Mutex::new(raw),
+            file: Some(file),
+            writable: true,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+            _writer_lock: writer_lock,
+        })))
     }
 
-    fn raw(&self, min_len: usize) -> io::Result<Arc<MmapRaw>> {
-        let mut raw = self.0 .0.lock().unwrap();
+    pub fn anonymous() -> Self {
+        Self(Arc::new(MappedFileInner {
+            raw: Mutex::new(Arc::new(Mapping::Anonymous(Vec::new()))),
+            file: None,
+            writable: true,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+            _writer_lock: None,
+        }))
+    }
+
+    pub fn read_only(file: File) -> io::Result<Self> {
+        let raw = Arc::new(Mapping::new(&file, false)?);
+        Ok(Self(Arc::new(MappedFileInner {
+            raw: Mutex::new(raw),
+            file: Some(file),
+            writable: false,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+            _writer_lock: None,
+        })))
+    }
+
+    fn raw(&self, min_len: usize) -> io::Result<Arc<Mapping>> {
+        let mut raw = self.0.raw.lock().unwrap();
         let len = raw.len();
         if len < min_len {
-            self.0 .1.set_len(min_len.max(len * 2) as u64)?;
-            *raw.deref_mut() = Arc::new(MmapRaw::map_raw(&self.0 .1)?)
+            let new_len = min_len.max(len * 2);
+            let mapping = match &self.0.file {
+                Some(file) => {
+                    if self.0.writable {
+                        file.set_len(new_len as u64)?;
+                    }
+                    Mapping::new(file, self.0.writable)?
+                }
+                None => raw.grow_anonymous(new_len)?,
+            };
+            *raw.deref_mut() = Arc::new(mapping);
+            if raw.len() < min_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "read-only mapping out of range",
+                ));
+            }
         }
         Ok(raw.clone())
     }
 
+    #[cfg(not(feature = "fault-injection"))]
     pub fn sync(&self) -> io::Result<()> {
-        self.0 .1.sync_data()
+        self.sync_file()
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub fn sync(&self) -> io::Result<()> {
+        match &self.0.faults {
+            Some(faults) => {
+                let raw = self.0.raw.lock().unwrap().clone();
+                faults.sync(unsafe { raw.as_slice() })
+            }
+            None => self.sync_file(),
+        }
+    }
+
+    fn sync_file(&self) -> io::Result<()> {
+        match &self.0.file {
+            Some(file) => file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub fn crash(&self) -> Vec<u8> {
+        let faults = self.0.faults.as_ref().unwrap();
+        let raw = self.0.raw.lock().unwrap().clone();
+        faults.crash(unsafe { raw.as_slice() })
+    }
+
+    pub fn has_readers(&self) -> io::Result<bool> {
+        match &self.0.file {
+            Some(file) => flock::has_readers(file),
+            None => Ok(false),
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.0 .0.lock().unwrap().len()
+        self.0.raw.lock().unwrap().len()
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.0.writable
     }
 }
 
 #[derive(Clone)]
 struct MappedBuffer {
-    raw: Arc<MmapRaw>,
+    raw: Arc<Mapping>,
     file: MappedFile,
 }
 
@@ -55,7 +220,7 @@ impl MappedBuffer {
         Ok(Self { raw, file })
     }
 
-    fn grow(&mut self, min_len: usize) -> io::Result<Arc<MmapRaw>> {
+    fn grow(&mut self, min_len: usize) -> io::Result<Arc<Mapping>> {
         Ok(if self.len() < min_len {
             let mut raw = self.file.raw(min_len)?;
             swap(&mut self.raw, &mut raw);
@@ -69,8 +234,8 @@ impl MappedBuffer {
         self.raw.len()
     }
 
-    fn as_ptr(&self) -> *const u8 {
-        self.raw.as_ptr()
+    fn ptr(&self, offset: usize, len: usize) -> *const u8 {
+        self.raw.ptr(offset, len)
     }
 }
 
@@ -85,9 +250,8 @@ impl<T: Pod> MappedVec<T> {
         assert!(index < self.len());
         unsafe {
             self.0
-                .as_ptr()
+                .ptr(index * size_of::<T>(), size_of::<T>())
                 .cast::<UnsafeCell<T>>()
-                .add(index)
                 .as_ref()
                 .unwrap()
         }
@@ -97,7 +261,7 @@ impl<T: Pod> MappedVec<T> {
         self.0.len() / size_of::<T>()
     }
 
-    pub fn grow(&mut self, min_len: usize) -> io::Result<Arc<MmapRaw>> {
+    pub fn grow(&mut self, min_len: usize) -> io::Result<Arc<Mapping>> {
         self.0.grow(min_len * size_of::<T>())
     }
 }
@@ -113,9 +277,9 @@ pub struct MappedBitset(MappedVec<u8>);
 
 impl MappedBitset {
     pub fn new(len: usize) -> io::Result<Self> {
-        let file = tempfile::tempfile()?;
-        file.set_len(len as u64)?;
-        Ok(Self(MappedVec::new(MappedFile::new(file)?)?))
+        let mut bits = MappedVec::new(MappedFile::anonymous())?;
+        bits.grow(len)?;
+        Ok(Self(bits))
     }
 
     pub unsafe fn get(&mut self, index: usize) -> bool {
@@ -130,7 +294,7 @@ impl MappedBitset {
         self.0.len()
     }
 
-    pub fn grow(&mut self, min_len: usize) -> io::Result<Arc<MmapRaw>> {
+    pub fn grow(&mut self, min_len: usize) -> io::Result<Arc<Mapping>> {
         self.0.grow(min_len)
     }
 }
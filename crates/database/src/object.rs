@@ -1,10 +1,14 @@
 use std::io::{self, Read, Write};
 
-use crate::{header::Format, reference::DatabaseRef};
+use crate::{header::Format, migration::Migrations, reference::DatabaseRef};
 
 pub trait Object: Sized {
     fn format() -> Format;
 
+    fn migrations() -> Migrations {
+        Migrations::new()
+    }
+
     fn serialize(&mut self, writer: impl Write) -> io::Result<()>;
 
     fn deserialize(reader: impl Read, database: DatabaseRef) -> io::Result<Self>;
@@ -0,0 +1,123 @@
+use std::io::{self, Read, Write};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    reference::DatabaseRef,
+    serialize::{layout, DbSerialize},
+    vec::{Len, Vec},
+};
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Slot {
+    group: u64,
+    index: u64,
+}
+
+impl Slot {
+    const NONE: Self = Self {
+        group: u64::MAX,
+        index: u64::MAX,
+    };
+}
+
+pub struct OneToMany {
+    groups: std::vec::Vec<Vec<u64>>,
+    slots: Vec<Slot>,
+}
+
+impl OneToMany {
+    pub fn new(database: DatabaseRef, groups: usize) -> Self {
+        let mut group_vecs = std::vec::Vec::with_capacity(groups);
+        group_vecs.resize_with(groups, || Vec::new(database.clone()));
+        Self {
+            groups: group_vecs,
+            slots: Vec::new(database),
+        }
+    }
+
+    pub fn groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn count(&self, group: usize) -> usize {
+        self.groups[group].read().len()
+    }
+
+    pub fn group_of(&self, member: usize) -> Option<usize> {
+        let slots = self.slots.read();
+        if member < slots.len() && slots[member].group != u64::MAX {
+            Some(slots[member].group as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, member: usize) -> bool {
+        self.group_of(member).is_some()
+    }
+
+    pub fn iter(&self, group: usize) -> impl Iterator<Item = usize> + '_ {
+        let members = self.groups[group].read();
+        (0..members.len()).map(move |index| members[index] as usize)
+    }
+
+    pub fn insert(&mut self, group: usize, member: usize) -> bool {
+        if group >= self.groups.len() || self.contains(member) {
+            return false;
+        }
+        let mut slots = self.slots.write();
+        if member >= slots.len() {
+            slots.resize(member + 1, Slot::NONE);
+        }
+        let mut members = self.groups[group].write();
+        slots[member] = Slot {
+            group: group as u64,
+            index: members.len() as u64,
+        };
+        members.push(member as u64);
+        true
+    }
+
+    pub fn remove(&mut self, member: usize) -> Option<usize> {
+        let mut slots = self.slots.write();
+        if member >= slots.len() || slots[member].group == u64::MAX {
+            return None;
+        }
+        let slot = slots[member];
+        slots[member] = Slot::NONE;
+        let group = slot.group as usize;
+        let index = slot.index as usize;
+        let mut members = self.groups[group].write();
+        members.swap_remove(index);
+        if index < members.len() {
+            slots[members[index] as usize].index = slot.index;
+        }
+        Some(group)
+    }
+
+    pub fn move_to(&mut self, member: usize, group: usize) -> bool {
+        group < self.groups.len() && self.remove(member).is_some() && self.insert(group, member)
+    }
+}
+
+impl DbSerialize for OneToMany {
+    const LAYOUT: u64 = layout(
+        "database::OneToMany",
+        &[<std::vec::Vec<Vec<u64>>>::LAYOUT, <Vec<Slot>>::LAYOUT],
+    );
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        self.groups.serialize(writer)?;
+        self.slots.serialize(writer)?;
+        Ok(())
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        Ok(Self {
+            groups: DbSerialize::deserialize(reader, database.clone())?,
+            slots: Vec::deserialize(reader, database)?,
+        })
+    }
+}
@@ -5,9 +5,8 @@ use std::{
 };
 
 use bytemuck::{Pod, Zeroable};
-use memmap2::MmapRaw;
 
-use crate::mmap::{MappedBitset, MappedFile, MappedVec};
+use crate::mmap::{MappedBitset, MappedFile, MappedVec, Mapping};
 
 pub const PAGE_SIZE: usize = 8192;
 
@@ -42,7 +41,7 @@ pub struct Pager {
 
 pub struct Inner {
     pages: MappedVec<Page>,
-    old_raws: Vec<Arc<MmapRaw>>,
+    old_raws: Vec<Arc<Mapping>>,
     writable: MappedBitset,
 }
 
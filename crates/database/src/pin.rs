@@ -0,0 +1,37 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Default)]
+pub struct Pins(Arc<Mutex<BTreeMap<u64, usize>>>);
+
+impl Pins {
+    pub fn pin(&self, version: u64) -> Pin {
+        *self.0.lock().unwrap().entry(version).or_default() += 1;
+        Pin {
+            pins: self.clone(),
+            version,
+        }
+    }
+
+    pub fn oldest(&self) -> Option<u64> {
+        self.0.lock().unwrap().keys().next().copied()
+    }
+}
+
+pub struct Pin {
+    pins: Pins,
+    version: u64,
+}
+
+impl Drop for Pin {
+    fn drop(&mut self) {
+        let mut versions = self.pins.0.lock().unwrap();
+        let count = versions.get_mut(&self.version).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            versions.remove(&self.version);
+        }
+    }
+}
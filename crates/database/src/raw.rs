@@ -5,49 +5,87 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Mutex, MutexGuard,
     },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use bytemuck::cast_mut;
+use bytemuck::{cast_mut, cast_ref};
 
 use crate::{
     allocator::AllocatorState,
+    checksum::{is_checksum_page, Checksums},
+    container::Registry,
     file::FileHeader,
     header::{Format, Header, HeaderPage, State},
     mmap::{MappedBitset, MappedFile},
-    page::{Page, Pager, NULL_PAGE_NR, PAGE_SIZE},
-    sync::Synchronizer,
+    page::{Page, PageNr, Pager, NULL_PAGE_NR, PAGE_SIZE},
+    pin::{Pin, Pins},
+    retention::{RetainedSnapshot, Retention},
 };
 
 pub struct RawDatabase {
     allocator_state: Mutex<AllocatorState>,
+    retention: Mutex<Retention>,
     version: u64,
+    timestamp: u64,
+    format_version: u32,
     data: MappedFile,
     writable: MappedBitset,
-    synchronizer: Synchronizer,
     closing: AtomicBool,
+    registry: Mutex<Registry>,
+    checksums: Option<Checksums>,
+    pins: Pins,
+    _pin: Option<Pin>,
 }
 
 impl RawDatabase {
     fn new(
-        file: File,
+        data: MappedFile,
         format: &Format,
-        setup_header: impl FnOnce(&mut Header),
+        header: Option<Header>,
     ) -> io::Result<(Self, FileHeader)> {
-        let data = MappedFile::new(file)?;
         let writable = MappedBitset::new(data.len() / PAGE_SIZE)?;
         let pager = Pager::new(data.clone(), writable.clone());
-        let page = unsafe { pager.page_mut(NULL_PAGE_NR) };
-        let header_page = cast_mut::<Page, HeaderPage>(page);
-        setup_header(&mut header_page.header);
-        let state = header_page.header.validate(format)?;
+        let header = match header {
+            Some(header) => {
+                let page = unsafe { pager.page_mut(NULL_PAGE_NR) };
+                cast_mut::<Page, HeaderPage>(page).header = header;
+                header
+            }
+            None => {
+                let page = unsafe { pager.page(NULL_PAGE_NR) };
+                match Header::upgrade(page, format)? {
+                    Some(header) if data.is_writable() => {
+                        let page = unsafe { pager.page_mut(NULL_PAGE_NR) };
+                        cast_mut::<Page, HeaderPage>(page).header = header;
+                        data.sync()?;
+                        header
+                    }
+                    Some(header) => header,
+                    None => cast_ref::<Page, HeaderPage>(page).header,
+                }
+            }
+        };
+        let state = header.validate(format)?;
+        let checksums = if header.has_checksums() {
+            Some(Checksums::new())
+        } else {
+            None
+        };
+        let retention = unsafe { Retention::new(&state.allocator, &pager) };
         Ok((
             Self {
                 allocator_state: Mutex::new(state.allocator),
+                retention: Mutex::new(retention),
                 version: state.version,
-                synchronizer: Synchronizer::new(data.clone()),
+                timestamp: state.timestamp,
+                format_version: state.format_version,
                 data,
                 writable,
                 closing: AtomicBool::new(false),
+                registry: Mutex::new(Registry::default()),
+                checksums,
+                pins: Pins::default(),
+                _pin: None,
             },
             FileHeader {
                 root: state.root_nr,
@@ -56,44 +94,197 @@ impl RawDatabase {
         ))
     }
 
-    pub fn create(file: File, format: &Format) -> io::Result<Self> {
-        Ok(Self::new(file, format, |header| *header = Header::new(*format))?.0)
+    pub fn create(
+        file: File,
+        writer_lock: File,
+        format: &Format,
+        checksums: bool,
+    ) -> io::Result<Self> {
+        Self::create_mapped(MappedFile::locked(file, writer_lock)?, format, checksums)
+    }
+
+    pub fn create_mapped(data: MappedFile, format: &Format, checksums: bool) -> io::Result<Self> {
+        let header = Header::new(format, checksums);
+        Ok(Self::new(data, format, Some(header))?.0)
     }
 
-    pub fn open(file: File, format: &Format) -> io::Result<(Self, FileHeader)> {
-        Self::new(file, format, |_| {})
+    pub fn in_memory(format: &Format) -> io::Result<Self> {
+        Self::create_mapped(MappedFile::anonymous(), format, false)
+    }
+
+    pub fn open(file: File, writer_lock: File, format: &Format) -> io::Result<(Self, FileHeader)> {
+        Self::new(MappedFile::locked(file, writer_lock)?, format, None)
+    }
+
+    pub fn open_read_only(file: File, format: &Format) -> io::Result<(Self, FileHeader)> {
+        let data = MappedFile::read_only(file)?;
+        if data.len() < PAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "database file too short",
+            ));
+        }
+        let (raw, header) = Self::new(data, format, None)?;
+        if raw.format_version != format.version {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "database schema version {} must be migrated to version {} first",
+                    raw.format_version, format.version
+                ),
+            ));
+        }
+        raw.close();
+        Ok((raw, header))
+    }
+
+    pub fn reader(&self) -> io::Result<Self> {
+        Ok(Self {
+            allocator_state: Mutex::new(*self.allocator_state()),
+            retention: Mutex::new(Retention::default()),
+            version: self.version,
+            timestamp: self.timestamp,
+            format_version: self.format_version,
+            data: self.data.clone(),
+            writable: MappedBitset::new(0)?,
+            closing: AtomicBool::new(true),
+            registry: Mutex::new(Registry::default()),
+            checksums: self.checksums.as_ref().map(|_| Checksums::new()),
+            pins: Pins::default(),
+            _pin: Some(self.pins.pin(self.version)),
+        })
+    }
+
+    pub fn rollback(&self, format: &Format) -> io::Result<(Self, FileHeader)> {
+        let (mut raw, header) = Self::new(self.data.clone(), format, None)?;
+        raw.pins = self.pins.clone();
+        if let (Some(checksums), Some(current)) = (&raw.checksums, &self.checksums) {
+            checksums.set_verify(current.is_verifying())
+        }
+        Ok((raw, header))
     }
 
     pub fn pager(&self) -> Pager {
         Pager::new(self.data.clone(), self.writable.clone())
     }
 
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    pub fn set_format_version(&mut self, format_version: u32) {
+        self.format_version = format_version
+    }
+
+    pub fn pages(&self) -> usize {
+        self.data.len() / PAGE_SIZE
+    }
+
+    pub fn checksums(&self) -> Option<&Checksums> {
+        self.checksums.as_ref()
+    }
+
+    pub(crate) fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap()
+    }
+
     pub fn allocator_state(&self) -> MutexGuard<'_, AllocatorState> {
         self.allocator_state.lock().unwrap()
     }
 
-    pub fn snapshot(&mut self, root: FileHeader) -> io::Result<()> {
+    pub fn retention(&self) -> MutexGuard<'_, Retention> {
+        self.retention.lock().unwrap()
+    }
+
+    pub fn restore(&mut self, index: usize, used: &[bool]) -> Vec<PageNr> {
+        let pager = self.pager();
+        let checksums = self.checksums.is_some();
         let allocator_state = self.allocator_state.get_mut().unwrap();
-        allocator_state.swap();
-        self.version += 1;
+        let mut used = used.to_vec();
+        used.resize(allocator_state.last_page() as usize + 1, false);
+        for snapshot in &allocator_state.retained()[..index] {
+            unsafe {
+                for nr in snapshot
+                    .dead
+                    .entries(&pager)
+                    .chain(snapshot.dead.pages(&pager))
+                {
+                    used[nr as usize] = true;
+                }
+            }
+        }
+        allocator_state.restore(index);
+        self.retention.get_mut().unwrap().truncate(index + 1);
+        (AllocatorState::first_page()..=allocator_state.last_page())
+            .filter(|nr| !used[*nr as usize])
+            .filter(|nr| !checksums || !is_checksum_page(*nr))
+            .collect()
+    }
+
+    pub fn snapshot(&mut self, root: FileHeader, retain: bool) -> io::Result<()> {
         let pager = Pager::new(self.data.clone(), self.writable.clone());
+        let release = self
+            .pins
+            .oldest()
+            .map_or(true, |version| version >= self.version)
+            && !self.data.has_readers()?;
+        let allocator_state = self.allocator_state.get_mut().unwrap();
+        if let Some(checksums) = &self.checksums {
+            unsafe { checksums.update(&pager)? };
+        }
+        allocator_state.swap(release);
+        self.version += 1;
+        self.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        if retain {
+            allocator_state.retain(RetainedSnapshot::new(
+                self.version,
+                root.root,
+                root.len,
+                self.format_version,
+                allocator_state,
+            ));
+            unsafe {
+                self.retention
+                    .get_mut()
+                    .unwrap()
+                    .push(allocator_state, &pager)
+            };
+        }
+        // The pages must be durable before a header slot refers to them.
+        self.data.sync()?;
         let page = unsafe { pager.page_mut(NULL_PAGE_NR) };
         let header_page = cast_mut::<Page, HeaderPage>(page);
         header_page.header.snapshot(State::new(
             self.version,
+            self.timestamp,
             *allocator_state,
             root.root,
             root.len,
+            self.format_version,
         ));
         self.writable = MappedBitset::new(self.writable.len())?;
-        self.synchronizer.sync();
-        Ok(())
+        // Only report success once the new header slot is durable as well.
+        self.data.sync()
     }
 
     pub fn close(&self) {
         self.closing.store(true, Ordering::Relaxed);
     }
 
+    pub fn reopen(&self) {
+        self.closing.store(false, Ordering::Relaxed);
+    }
+
     pub fn is_closing(&self) -> bool {
         self.closing.load(Ordering::Relaxed)
     }
@@ -2,7 +2,10 @@ use std::{io, sync::Arc};
 
 use atomic_refcell::AtomicRefCell;
 
-use crate::{file::FileHeader, lock::Lock, raw::RawDatabase};
+use crate::{
+    allocator::AllocatorState, container::Container, file::FileHeader, header::Format, lock::Lock,
+    page::PageNr, raw::RawDatabase, retention::RetainedSnapshot,
+};
 
 #[derive(Clone)]
 pub struct DatabaseRef(Arc<AtomicRefCell<RawDatabase>>);
@@ -16,7 +19,84 @@ impl DatabaseRef {
         Lock::new(self.0.borrow())
     }
 
-    pub(crate) fn snapshot(&self, root: FileHeader) -> io::Result<()> {
-        self.0.borrow_mut().snapshot(root)
+    pub(crate) fn register(&self, container: Container) -> PageNr {
+        self.0.borrow().registry().register(container)
+    }
+
+    pub(crate) fn containers(&self) -> Vec<Container> {
+        self.0.borrow().registry().take()
+    }
+
+    pub(crate) fn fields(&self) -> Vec<(&'static str, Vec<Container>)> {
+        self.0.borrow().registry().take_fields()
+    }
+
+    /// Marks the start of a top-level object field while deserializing, so
+    /// statistics can attribute containers to it. Only used by derived code.
+    #[doc(hidden)]
+    pub fn begin_field(&self, name: &'static str) {
+        self.0.borrow().registry().begin_field(name)
+    }
+
+    pub(crate) fn relocate(&self, from: PageNr, to: PageNr) {
+        self.0.borrow().registry().relocate(from, to)
+    }
+
+    pub(crate) fn last_page(&self) -> PageNr {
+        self.0.borrow().allocator_state().last_page()
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.0.borrow().version()
+    }
+
+    pub(crate) fn timestamp(&self) -> u64 {
+        self.0.borrow().timestamp()
+    }
+
+    pub(crate) fn pages(&self) -> usize {
+        self.0.borrow().pages()
+    }
+
+    pub(crate) fn allocator_state(&self) -> AllocatorState {
+        *self.0.borrow().allocator_state()
+    }
+
+    pub(crate) fn snapshots(&self) -> Vec<RetainedSnapshot> {
+        self.0.borrow().allocator_state().retained().to_vec()
+    }
+
+    pub(crate) fn format_version(&self) -> u32 {
+        self.0.borrow().format_version()
+    }
+
+    pub(crate) fn set_format_version(&self, format_version: u32) {
+        self.0.borrow_mut().set_format_version(format_version)
+    }
+
+    pub(crate) fn set_verify(&self, verify: bool) {
+        if let Some(checksums) = self.0.borrow().checksums() {
+            checksums.set_verify(verify)
+        }
+    }
+
+    pub(crate) fn reader(&self) -> io::Result<RawDatabase> {
+        self.0.borrow().reader()
+    }
+
+    pub(crate) fn rollback(&self, format: &Format) -> io::Result<(RawDatabase, FileHeader)> {
+        self.0.borrow().rollback(format)
+    }
+
+    pub(crate) fn restore(&self, index: usize, used: &[bool]) {
+        let pages = self.0.borrow_mut().restore(index, used);
+        let lock = self.lock();
+        for nr in pages {
+            unsafe { lock.deallocate(nr) }
+        }
+    }
+
+    pub(crate) fn snapshot(&self, root: FileHeader, retain: bool) -> io::Result<()> {
+        self.0.borrow_mut().snapshot(root, retain)
     }
 }
@@ -0,0 +1,93 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    allocator::AllocatorState,
+    free_list::FreeList,
+    page::{PageNr, Pager},
+};
+
+pub const MAX_RETAINED: usize = 16;
+
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct RetainedSnapshot {
+    pub version: u64,
+    pub root_len: u64,
+    pub root_nr: PageNr,
+    pub format_version: u32,
+    last_page: PageNr,
+    previous_free: FreeList,
+    current_free: FreeList,
+    pub dead: FreeList,
+}
+
+impl RetainedSnapshot {
+    pub fn new(
+        version: u64,
+        root_nr: PageNr,
+        root_len: u64,
+        format_version: u32,
+        state: &AllocatorState,
+    ) -> Self {
+        let (previous_free, current_free) = state.free_lists();
+        Self {
+            version,
+            root_len,
+            root_nr,
+            format_version,
+            last_page: state.last_page(),
+            previous_free,
+            current_free,
+            dead: FreeList::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Retention(Vec<Vec<bool>>);
+
+impl Retention {
+    pub unsafe fn new(state: &AllocatorState, pager: &Pager) -> Self {
+        let mut retention = Self::default();
+        while retention.0.len() < state.retained().len() {
+            retention.push(state, pager);
+        }
+        retention
+    }
+
+    pub unsafe fn push(&mut self, state: &AllocatorState, pager: &Pager) {
+        let retained = state.retained();
+        let index = self.0.len();
+        let snapshot = &retained[index];
+        let mut free = vec![false; snapshot.last_page as usize + 1];
+        let lists = retained[..index]
+            .iter()
+            .map(|older| &older.dead)
+            .chain([&snapshot.previous_free, &snapshot.current_free]);
+        for list in lists {
+            for nr in list.entries(pager) {
+                if let Some(free) = free.get_mut(nr as usize) {
+                    *free = true;
+                }
+            }
+        }
+        self.0.push(free);
+    }
+
+    pub fn newest(&self) -> Option<usize> {
+        self.0.len().checked_sub(1)
+    }
+
+    pub fn retains(&self, index: usize, nr: PageNr) -> bool {
+        let free = &self.0[index];
+        free.get(nr as usize).map_or(false, |free| !free)
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.0.remove(index);
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len)
+    }
+}
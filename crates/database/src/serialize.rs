@@ -0,0 +1,153 @@
+use std::{
+    hash::Hash,
+    io::{self, ErrorKind, Read, Write},
+    mem::{align_of, size_of},
+};
+
+use bytemuck::Pod;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    blob::BlobTree, file::File, hash_map::HashMap, reference::DatabaseRef, tree::Tree, vec::Vec,
+};
+
+pub trait DbSerialize: Sized {
+    const LAYOUT: u64;
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()>;
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self>;
+}
+
+pub const fn layout(name: &str, parts: &[u64]) -> u64 {
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(PRIME);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < parts.len() {
+        let bytes = parts[i].to_le_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            hash = (hash ^ bytes[j] as u64).wrapping_mul(PRIME);
+            j += 1;
+        }
+        i += 1;
+    }
+    hash
+}
+
+const fn pod_layout<T>() -> u64 {
+    layout("", &[size_of::<T>() as u64, align_of::<T>() as u64])
+}
+
+pub fn write_layout(writer: &mut impl Write, layout: u64) -> io::Result<()> {
+    writer.write_all(&layout.to_le_bytes())
+}
+
+pub fn read_layout(reader: &mut impl Read, layout: u64) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    if u64::from_le_bytes(bytes) != layout {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "database layout does not match the object definition",
+        ));
+    }
+    Ok(())
+}
+
+pub fn serialize_value<T: Serialize>(value: &T, writer: &mut impl Write) -> io::Result<()> {
+    bincode::serialize_into(writer, value).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn deserialize_value<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<T> {
+    bincode::deserialize_from(reader).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+impl<T: Pod> DbSerialize for Vec<T> {
+    const LAYOUT: u64 = layout("database::Vec", &[pod_layout::<T>()]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        Vec::serialize(self, writer)
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        Vec::deserialize(reader, database)
+    }
+}
+
+impl<K: Pod + Ord, V: Pod> DbSerialize for Tree<K, V> {
+    const LAYOUT: u64 = layout("database::Tree", &[pod_layout::<K>(), pod_layout::<V>()]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        Tree::serialize(self, writer)
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        Tree::deserialize(reader, database)
+    }
+}
+
+impl<K: Pod + Hash + Eq, V: Pod> DbSerialize for HashMap<K, V> {
+    const LAYOUT: u64 = layout("database::HashMap", &[pod_layout::<K>(), pod_layout::<V>()]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        HashMap::serialize(self, writer)
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        HashMap::deserialize(reader, database)
+    }
+}
+
+impl DbSerialize for BlobTree {
+    const LAYOUT: u64 = layout("database::BlobTree", &[]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        BlobTree::serialize(self, writer)
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        BlobTree::deserialize(reader, database)
+    }
+}
+
+impl DbSerialize for File {
+    const LAYOUT: u64 = layout("database::File", &[]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        File::serialize(self, writer)
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        File::deserialize(reader, database)
+    }
+}
+
+impl<T: DbSerialize> DbSerialize for std::vec::Vec<T> {
+    const LAYOUT: u64 = layout("Vec", &[T::LAYOUT]);
+
+    fn serialize(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&(self.len() as u64).to_le_bytes())?;
+        for value in self.iter_mut() {
+            value.serialize(writer)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        let len = u64::from_le_bytes(bytes);
+        let mut values = Self::new();
+        for _ in 0..len {
+            values.push(T::deserialize(reader, database.clone())?);
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,53 @@
+use util::inspect::{Inspect, Inspector};
+
+#[derive(Clone, Debug, Default, Inspect)]
+pub struct Stats {
+    pub version: u64,
+    pub timestamp: u64,
+    pub format_version: u64,
+    pub pages: PageStats,
+    pub fields: FieldStats,
+}
+
+#[derive(Clone, Copy, Debug, Default, Inspect)]
+pub struct PageStats {
+    pub total: u64,
+    pub live: u64,
+    pub last_page: u64,
+    pub previous_free: u64,
+    pub current_free: u64,
+    pub retained_snapshots: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Inspect)]
+pub struct ContainerStats {
+    pub pages: u64,
+    pub bytes: u64,
+}
+
+/// Storage used by each top-level field of the object, in declaration order.
+#[derive(Clone, Debug, Default)]
+pub struct FieldStats(pub Vec<(&'static str, ContainerStats)>);
+
+impl FieldStats {
+    pub fn get(&self, name: &str) -> Option<ContainerStats> {
+        self.0
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, stats)| *stats)
+    }
+}
+
+impl Inspect for FieldStats {
+    fn inspect(&self, name: &str, inspector: &mut impl Inspector) {
+        inspector.inspect(name, |inspector| {
+            for (field, stats) in self.0.iter() {
+                stats.inspect(field, inspector);
+            }
+        })
+    }
+
+    fn inspect_mut(&mut self, name: &str, inspector: &mut impl Inspector) {
+        self.inspect(name, inspector)
+    }
+}
@@ -60,6 +60,13 @@ impl<K: Pod + Ord> Branch<K> {
         (page_nr, Self::wrap_mut(page))
     }
 
+    pub unsafe fn allocate_root<'a>(child: PageNr, lock: &'a Lock) -> (PageNr, &'a mut Self) {
+        let (page_nr, branch) = Self::allocate(lock);
+        branch.page.set_len(1);
+        branch.children_mut()[0] = child;
+        (page_nr, branch)
+    }
+
     pub fn keys(&self) -> &[K] {
         cast_slice(&self.page[0..(self.page.len() - 1) * size_of::<K>()])
     }
@@ -151,21 +158,21 @@ impl<K: Pod + Ord> Branch<K> {
     pub unsafe fn shift_left<'a, V: Pod>(&mut self, right: &mut Self, lock: &'a Lock) -> K {
         let left_key = right.left_key::<V>(lock);
         let key = right.keys()[0];
-        self.insert_right(self.page.len(), left_key, right.children()[0]);
+        self.insert_right(self.page.len() - 1, left_key, right.children()[0]);
         right.delete_left(0);
         key
     }
 
     pub unsafe fn shift_right<'a, V: Pod>(&mut self, right: &mut Self, lock: &'a Lock) -> K {
         let left_key = right.left_key::<V>(lock);
-        let index = self.page.len() - 1;
+        let index = self.page.len() - 2;
         let key = self.keys()[index];
-        right.insert_left(0, left_key, self.children()[index - 1]);
+        right.insert_left(0, left_key, self.children()[index + 1]);
         self.delete_right(index);
         key
     }
 
-    pub unsafe fn merge<'a, V: Pod>(&mut self, right: &mut Self, lock: &'a Lock) {
+    pub unsafe fn merge<'a, V: Pod>(&mut self, right: &Self, lock: &'a Lock) {
         let key = right.left_key::<V>(lock);
         let len = self.page.len();
         self.page.set_len(len + right.page.len());
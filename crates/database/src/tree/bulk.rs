@@ -0,0 +1,125 @@
+use std::io::{self, ErrorKind};
+
+use bytemuck::{Pod, TransparentWrapper};
+
+use crate::{
+    lock::Lock,
+    page::{PageNr, NULL_PAGE_NR},
+};
+
+use super::{branch::Branch, leaf::Leaf};
+
+pub fn load<K: Pod + Ord, V: Pod, I: IntoIterator<Item = (K, V)>>(
+    entries: I,
+    fill: f32,
+    lock: &Lock,
+) -> io::Result<PageNr> {
+    if !(fill > 0.0 && fill <= 1.0) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "fill factor must be in (0, 1]",
+        ));
+    }
+    let mut nodes = unsafe { load_leaves::<K, V, I>(entries, fill, lock)? };
+    if nodes.is_empty() {
+        return Ok(NULL_PAGE_NR);
+    }
+    while nodes.len() > 1 {
+        nodes = unsafe { load_branches(&nodes, fill, lock) };
+    }
+    Ok(nodes[0].1)
+}
+
+fn fill_len(order: usize, fill: f32) -> usize {
+    ((order as f32 * fill) as usize)
+        .max((order + 1) / 2)
+        .min(order)
+}
+
+unsafe fn load_leaves<K: Pod + Ord, V: Pod, I: IntoIterator<Item = (K, V)>>(
+    entries: I,
+    fill: f32,
+    lock: &Lock,
+) -> io::Result<Vec<(K, PageNr)>> {
+    let order = Leaf::<K, V>::order();
+    let len = fill_len(order, fill);
+    let mut nodes = Vec::new();
+    let mut current: Option<&mut Leaf<K, V>> = None;
+    let mut previous_key = None;
+    for (key, value) in entries {
+        if previous_key.map_or(false, |previous_key| previous_key >= key) {
+            for (_, page_nr) in nodes {
+                lock.deallocate(page_nr);
+            }
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "bulk load keys must be strictly ascending",
+            ));
+        }
+        previous_key = Some(key);
+        let leaf = match current.take() {
+            Some(leaf) if leaf.len() < len => leaf,
+            _ => {
+                let (page_nr, leaf) = Leaf::allocate(lock);
+                nodes.push((key, page_nr));
+                leaf
+            }
+        };
+        leaf.insert(leaf.len(), key, value);
+        current = Some(leaf);
+    }
+    if let Some(last) = current {
+        if nodes.len() > 1 && last.len() < order / 2 {
+            let left_nr = nodes[nodes.len() - 2].1;
+            let left = Leaf::<K, V>::wrap_mut(lock.try_page_mut(left_nr).unwrap());
+            let total = left.len() + last.len();
+            if total <= order {
+                left.merge(last);
+                let (_, last_nr) = nodes.pop().unwrap();
+                lock.deallocate(last_nr);
+            } else {
+                while last.len() < total / 2 {
+                    left.shift_right(last);
+                }
+                nodes.last_mut().unwrap().0 = last.keys()[0];
+            }
+        }
+    }
+    Ok(nodes)
+}
+
+unsafe fn load_branches<K: Pod + Ord>(
+    children: &[(K, PageNr)],
+    fill: f32,
+    lock: &Lock,
+) -> Vec<(K, PageNr)> {
+    let order = Branch::<K>::order();
+    let mut nodes = Vec::new();
+    let mut children = children;
+    for len in chunk_lens(children.len(), fill_len(order, fill), order) {
+        let (chunk, rest) = children.split_at(len);
+        let (page_nr, branch) = Branch::allocate_root(chunk[0].1, lock);
+        for (key, child) in &chunk[1..] {
+            branch.insert_right(branch.len() - 1, *key, *child);
+        }
+        nodes.push((chunk[0].0, page_nr));
+        children = rest;
+    }
+    nodes
+}
+
+fn chunk_lens(count: usize, len: usize, order: usize) -> Vec<usize> {
+    let chunks = (count + len - 1) / len;
+    let mut lens = vec![len; chunks];
+    lens[chunks - 1] = count - len * (chunks - 1);
+    if chunks > 1 && lens[chunks - 1] < order / 2 {
+        let total = lens.pop().unwrap() + lens.pop().unwrap();
+        if total <= order {
+            lens.push(total);
+        } else {
+            lens.push(total - total / 2);
+            lens.push(total / 2);
+        }
+    }
+    lens
+}
@@ -74,6 +74,18 @@ impl<'a, R: Deref<Target = PageNr>, K: Pod + Ord, V: Pod> Cursor<'a, R, K, V> {
         }
     }
 
+    pub fn key<'b: 'a>(&self, lock: &'b Lock) -> Option<&'b K> {
+        if self.key.is_none() {
+            Some(&self.leaf(lock).keys()[self.entries[0].index])
+        } else {
+            None
+        }
+    }
+
+    pub fn missing_key(&self) -> Option<&'a K> {
+        self.key
+    }
+
     pub fn has_value(&self) -> bool {
         self.key.is_none()
     }
@@ -166,7 +178,7 @@ impl<'a, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> Cursor<'a, R, K, V>
                 leaf.split(other);
                 let len = leaf.len();
                 self.parent_insert(0, other.keys()[0], page_nr, lock);
-                if index < len {
+                if index <= len {
                     leaf.insert(index, *key, value);
                 } else {
                     let index = index - len;
@@ -185,8 +197,7 @@ impl<'a, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> Cursor<'a, R, K, V>
 
     unsafe fn parent_insert(&mut self, level: usize, key: K, value: PageNr, lock: &'a Lock) {
         if level == self.root_level() {
-            let (page_nr, branch) = Branch::allocate(lock);
-            branch.children_mut()[0] = *self.root;
+            let (page_nr, branch) = Branch::allocate_root(*self.root, lock);
             branch.insert_right(0, key, value);
             *self.root = page_nr;
             self.entries.push(Entry { page_nr, index: 0 });
@@ -275,36 +286,38 @@ impl<'a, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> Cursor<'a, R, K, V>
         }
         if index > 0 {
             let child = &mut branch.children_mut()[index - 1];
+            let page = self.page(level, lock);
             if level == 0 {
-                let left_leaf = Leaf::<K, V>::wrap_mut(lock.page_mut(child));
-                let child = *child;
-                left_leaf.merge(self.leaf_mut(lock));
-                branch.delete_left(index);
-                self.entries[level + 1].index -= 1;
-                lock.deallocate(child);
+                Leaf::<K, V>::wrap_mut(lock.page_mut(child)).merge(Leaf::wrap_ref(page));
             } else {
-                let left_branch = Branch::wrap_mut(lock.page_mut(child));
-                let child = *child;
-                left_branch.merge::<V>(self.branch_mut(level, lock), lock);
-                branch.delete_left(index);
-                self.entries[level + 1].index -= 1;
-                lock.deallocate(child);
+                Branch::<K>::wrap_mut(lock.page_mut(child))
+                    .merge::<V>(Branch::wrap_ref(page), lock);
             }
+            let child = *child;
+            branch.delete_right(index - 1);
+            lock.deallocate(self.entries[level].page_nr);
+            self.entries[level] = Entry {
+                page_nr: child,
+                index: 0,
+            };
+            self.entries[level + 1].index -= 1;
         } else {
-            let child = &mut branch.children_mut()[index + 1];
+            let child = branch.children()[index + 1];
+            let page = lock.page(child);
             if level == 0 {
-                let right_leaf = Leaf::wrap_mut(lock.page_mut(child));
-                self.leaf_mut(lock).merge(right_leaf);
+                self.leaf_mut(lock).merge(Leaf::wrap_ref(page));
             } else {
-                let right_branch = Branch::wrap_mut(lock.page_mut(child));
-                self.branch_mut(level, lock).merge::<V>(right_branch, lock);
+                self.branch_mut(level, lock)
+                    .merge::<V>(Branch::wrap_ref(page), lock);
             }
             branch.delete_right(index);
-            lock.deallocate(self.entries[level + 1].page_nr);
+            lock.deallocate(child);
         }
-        if level + 1 == self.root_level() && branch.len() == 1 {
-            *self.root = branch.children()[0];
-            self.entries.pop();
+        if level + 1 == self.root_level() {
+            if branch.len() == 1 {
+                *self.root = branch.children()[0];
+                lock.deallocate(self.entries.pop().unwrap().page_nr);
+            }
         } else if branch.len() * 2 < Branch::<K>::order() {
             self.rebalance(level + 1, lock);
         }
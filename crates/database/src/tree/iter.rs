@@ -0,0 +1,253 @@
+use std::{marker::PhantomData, ops::Bound};
+
+use bytemuck::{Pod, TransparentWrapper};
+
+use crate::{
+    lock::Lock,
+    page::{PageNr, NULL_PAGE_NR},
+};
+
+use super::{
+    branch::Branch,
+    leaf::Leaf,
+    node::{node, NodeRef},
+};
+
+pub struct Iter<'a, 'b, K: Pod + Ord, V: Pod> {
+    front: Option<Path<K, V>>,
+    back: Option<Path<K, V>>,
+    lock: &'a Lock<'b>,
+}
+
+impl<'a, 'b, K: Pod + Ord, V: Pod> Iter<'a, 'b, K, V> {
+    pub(super) fn new(root: PageNr, start: Bound<&K>, end: Bound<&K>, lock: &'a Lock<'b>) -> Self {
+        let front = Path::lower_bound(root, start, lock);
+        let back = Path::upper_bound(root, end, lock);
+        let (front, back) = match (front, back) {
+            (Some(front), Some(back)) if front.key(lock) <= back.key(lock) => {
+                (Some(front), Some(back))
+            }
+            _ => (None, None),
+        };
+        Self { front, back, lock }
+    }
+
+    fn finish_if_met(&mut self) -> bool {
+        let met = match (&self.front, &self.back) {
+            (Some(front), Some(back)) => front.leaf_entry() == back.leaf_entry(),
+            _ => true,
+        };
+        if met {
+            self.front = None;
+            self.back = None;
+        }
+        met
+    }
+}
+
+impl<'a, 'b, K: Pod + Ord, V: Pod> Iterator for Iter<'a, 'b, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.front.as_ref()?.get(self.lock);
+        if !self.finish_if_met() && !self.front.as_mut().unwrap().advance(self.lock) {
+            self.front = None;
+            self.back = None;
+        }
+        Some(item)
+    }
+}
+
+impl<'a, 'b, K: Pod + Ord, V: Pod> DoubleEndedIterator for Iter<'a, 'b, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.back.as_ref()?.get(self.lock);
+        if !self.finish_if_met() && !self.back.as_mut().unwrap().retreat(self.lock) {
+            self.front = None;
+            self.back = None;
+        }
+        Some(item)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    page_nr: PageNr,
+    index: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Left,
+    Right,
+}
+
+struct Path<K, V> {
+    entries: Vec<Entry>,
+    _phantom_key: PhantomData<K>,
+    _phantom_value: PhantomData<V>,
+}
+
+impl<K: Pod + Ord, V: Pod> Path<K, V> {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            _phantom_key: PhantomData,
+            _phantom_value: PhantomData,
+        }
+    }
+
+    pub fn lower_bound(root: PageNr, bound: Bound<&K>, lock: &Lock) -> Option<Self> {
+        if root == NULL_PAGE_NR {
+            return None;
+        }
+        let mut path = Self::new();
+        match bound {
+            Bound::Included(key) => {
+                path.descend_to(root, key, lock);
+            }
+            Bound::Excluded(key) => {
+                if path.descend_to(root, key, lock) {
+                    path.entries.last_mut().unwrap().index += 1;
+                }
+            }
+            Bound::Unbounded => path.descend(root, Edge::Left, lock),
+        }
+        if path.seek(lock) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    pub fn upper_bound(root: PageNr, bound: Bound<&K>, lock: &Lock) -> Option<Self> {
+        if root == NULL_PAGE_NR {
+            return None;
+        }
+        let mut path = Self::new();
+        let valid = match bound {
+            Bound::Included(key) => path.descend_to(root, key, lock) || path.retreat(lock),
+            Bound::Excluded(key) => {
+                path.descend_to(root, key, lock);
+                path.retreat(lock)
+            }
+            Bound::Unbounded => {
+                path.descend(root, Edge::Right, lock);
+                path.retreat(lock)
+            }
+        };
+        if valid {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    pub fn get<'a>(&self, lock: &'a Lock) -> (&'a K, &'a V) {
+        let entry = self.leaf_entry();
+        let leaf = Leaf::<K, V>::wrap_ref(unsafe { lock.page(entry.page_nr) });
+        (&leaf.keys()[entry.index], &leaf.values()[entry.index])
+    }
+
+    fn key<'a>(&self, lock: &'a Lock) -> &'a K {
+        self.get(lock).0
+    }
+
+    fn leaf_entry(&self) -> Entry {
+        *self.entries.last().unwrap()
+    }
+
+    pub fn advance(&mut self, lock: &Lock) -> bool {
+        self.entries.last_mut().unwrap().index += 1;
+        self.seek(lock)
+    }
+
+    fn seek(&mut self, lock: &Lock) -> bool {
+        loop {
+            let entry = self.leaf_entry();
+            if entry.index < Leaf::<K, V>::wrap_ref(unsafe { lock.page(entry.page_nr) }).len() {
+                return true;
+            }
+            self.entries.pop();
+            let child = loop {
+                let entry = match self.entries.last_mut() {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                let branch = Branch::<K>::wrap_ref(unsafe { lock.page(entry.page_nr) });
+                entry.index += 1;
+                if entry.index < branch.len() {
+                    break branch.children()[entry.index];
+                }
+                self.entries.pop();
+            };
+            self.descend(child, Edge::Left, lock);
+        }
+    }
+
+    pub fn retreat(&mut self, lock: &Lock) -> bool {
+        loop {
+            let entry = self.entries.last_mut().unwrap();
+            if entry.index > 0 {
+                entry.index -= 1;
+                return true;
+            }
+            self.entries.pop();
+            let child = loop {
+                let entry = match self.entries.last_mut() {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                if entry.index > 0 {
+                    entry.index -= 1;
+                    let branch = Branch::<K>::wrap_ref(unsafe { lock.page(entry.page_nr) });
+                    break branch.children()[entry.index];
+                }
+                self.entries.pop();
+            };
+            self.descend(child, Edge::Right, lock);
+        }
+    }
+
+    fn descend(&mut self, mut page_nr: PageNr, edge: Edge, lock: &Lock) {
+        loop {
+            match node::<K, V>(unsafe { lock.page(page_nr) }) {
+                NodeRef::Branch(branch) => {
+                    let index = match edge {
+                        Edge::Left => 0,
+                        Edge::Right => branch.len() - 1,
+                    };
+                    self.entries.push(Entry { page_nr, index });
+                    page_nr = branch.children()[index];
+                }
+                NodeRef::Leaf(leaf) => {
+                    let index = match edge {
+                        Edge::Left => 0,
+                        Edge::Right => leaf.len(),
+                    };
+                    self.entries.push(Entry { page_nr, index });
+                    return;
+                }
+            }
+        }
+    }
+
+    fn descend_to(&mut self, mut page_nr: PageNr, key: &K, lock: &Lock) -> bool {
+        loop {
+            match node::<K, V>(unsafe { lock.page(page_nr) }) {
+                NodeRef::Branch(branch) => {
+                    let index = branch.search(key);
+                    self.entries.push(Entry { page_nr, index });
+                    page_nr = branch.children()[index];
+                }
+                NodeRef::Leaf(leaf) => {
+                    let (index, found) = match leaf.search(key) {
+                        Ok(index) => (index, true),
+                        Err(index) => (index, false),
+                    };
+                    self.entries.push(Entry { page_nr, index });
+                    return found;
+                }
+            }
+        }
+    }
+}
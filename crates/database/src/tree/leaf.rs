@@ -116,7 +116,7 @@ impl<K: Pod + Ord, V: Pod> Leaf<K, V> {
     }
 
     pub unsafe fn shift_left(&mut self, right: &mut Self) -> K {
-        self.insert(self.page.len() - 1, right.keys()[0], right.values()[0]);
+        self.insert(self.page.len(), right.keys()[0], right.values()[0]);
         right.delete(0);
         right.keys()[0]
     }
@@ -129,7 +129,7 @@ impl<K: Pod + Ord, V: Pod> Leaf<K, V> {
         key
     }
 
-    pub unsafe fn merge(&mut self, right: &mut Self) {
+    pub unsafe fn merge(&mut self, right: &Self) {
         let len = self.page.len();
         self.page.set_len(len + right.page.len());
         self.keys_mut()[len..].copy_from_slice(right.keys());
@@ -1,22 +1,31 @@
 mod branch;
+mod bulk;
 mod cursor;
+mod iter;
 mod leaf;
 mod node;
+mod walk;
 
 use std::{
     io::{self, Read, Write},
     marker::PhantomData,
-    ops::{Deref, DerefMut},
+    mem::replace,
+    ops::{Deref, DerefMut, RangeBounds},
 };
 
 use bytemuck::Pod;
 
 use crate::{
+    check::Checker,
+    compact::Copier,
+    container::{Container, Walk},
     lock::Lock,
     page::{PageNr, NULL_PAGE_NR},
     reference::DatabaseRef,
 };
 
+pub use self::iter::Iter;
+
 use self::{cursor::Cursor, node::NodeRef};
 
 pub struct Tree<K: Pod + Ord, V: Pod> {
@@ -36,10 +45,27 @@ impl<K: Pod + Ord, V: Pod> Tree<K, V> {
         }
     }
 
+    pub fn from_sorted_iter(
+        database: DatabaseRef,
+        entries: impl IntoIterator<Item = (K, V)>,
+        fill: f32,
+    ) -> io::Result<Self> {
+        let mut tree = Self::new(database);
+        tree.write().bulk_load(entries, fill)?;
+        Ok(tree)
+    }
+
     pub fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
+        Self::deserialize_with::<Self, _>(reader, database)
+    }
+
+    pub(crate) fn deserialize_with<W: Walk, R: Read>(
+        reader: &mut R,
+        database: DatabaseRef,
+    ) -> io::Result<Self> {
         let mut bytes = [0; 4];
         reader.read_exact(&mut bytes)?;
-        let root = u32::from_ne_bytes(bytes);
+        let root = database.register(Container::new::<W>(u32::from_le_bytes(bytes), 0));
         Ok(Self {
             root,
             database,
@@ -49,10 +75,26 @@ impl<K: Pod + Ord, V: Pod> Tree<K, V> {
     }
 
     pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
-        writer.write_all(&self.root.to_ne_bytes())?;
+        writer.write_all(&self.root.to_le_bytes())?;
         Ok(())
     }
 
+    pub(crate) fn check_with(
+        checker: &mut Checker,
+        root: PageNr,
+        value: &mut impl FnMut(&mut Checker, &V),
+    ) {
+        walk::check::<K, V, _>(root, checker, value)
+    }
+
+    pub(crate) fn copy_with(
+        copier: &mut Copier,
+        root: PageNr,
+        value: &mut impl FnMut(&mut Copier, &mut V),
+    ) -> PageNr {
+        walk::copy::<K, V, _>(root, copier, value)
+    }
+
     pub fn read(&self) -> ReadTreeGuard<'_, K, V> {
         ReadTreeGuard {
             root: &self.root,
@@ -91,8 +133,8 @@ impl<'a, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> TreeGuard<'a, R, K,
         let cursor = Cursor::new(self.root.deref_mut(), key, &self.lock);
         if cursor.has_value() {
             Entry::Occupied(OccupiedEntry {
-                _cursor: cursor,
-                _lock: &self.lock,
+                cursor,
+                lock: &self.lock,
             })
         } else {
             Entry::Vacant(VacantEntry {
@@ -102,6 +144,17 @@ impl<'a, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> TreeGuard<'a, R, K,
         }
     }
 
+    pub fn bulk_load(
+        &mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        fill: f32,
+    ) -> io::Result<()> {
+        let root = bulk::load(entries, fill, &self.lock)?;
+        self.clear();
+        *self.root = root;
+        Ok(())
+    }
+
     pub fn clear(&mut self) {
         if *self.root == NULL_PAGE_NR {
             return;
@@ -120,6 +173,31 @@ impl<'a, R: Deref<Target = PageNr>, K: Pod + Ord, V: Pod> TreeGuard<'a, R, K, V>
     pub fn get(&self, key: &K) -> Option<&V> {
         Cursor::new(self.root.deref(), key, &self.lock).value(&self.lock)
     }
+
+    pub fn iter(&self) -> Iter<'_, 'a, K, V> {
+        self.range(..)
+    }
+
+    pub fn range(&self, range: impl RangeBounds<K>) -> Iter<'_, 'a, K, V> {
+        Iter::new(
+            *self.root,
+            range.start_bound(),
+            range.end_bound(),
+            &self.lock,
+        )
+    }
+
+    pub(crate) fn lock(&self) -> &Lock<'a> {
+        &self.lock
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
 }
 
 pub struct TreeGuard<'a, R: Deref<Target = PageNr>, K: Pod + Ord, V: Pod> {
@@ -132,6 +210,16 @@ pub struct TreeGuard<'a, R: Deref<Target = PageNr>, K: Pod + Ord, V: Pod> {
 pub type ReadTreeGuard<'a, K, V> = TreeGuard<'a, &'a PageNr, K, V>;
 pub type WriteTreeGuard<'a, K, V> = TreeGuard<'a, &'a mut PageNr, K, V>;
 
+impl<K: Pod + Ord, V: Pod> Walk for Tree<K, V> {
+    fn check(checker: &mut Checker, root: PageNr, _: u64) {
+        Self::check_with(checker, root, &mut |_, _| {})
+    }
+
+    fn copy(copier: &mut Copier, root: PageNr, _: u64) -> PageNr {
+        Self::copy_with(copier, root, &mut |_, _| {})
+    }
+}
+
 impl<K: Pod + Ord, V: Pod> Drop for Tree<K, V> {
     fn drop(&mut self) {
         let lock = self.database.lock();
@@ -147,9 +235,75 @@ pub enum Entry<'a, 'b, 'c, R: DerefMut<Target = PageNr>, K: 'a + Pod + Ord, V: '
     Vacant(VacantEntry<'a, 'b, 'c, R, K, V>),
 }
 
-pub struct OccupiedEntry<'a, 'b, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> {
-    _cursor: Cursor<'a, R, K, V>,
-    _lock: &'b Lock<'c>,
+impl<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod>
+    Entry<'a, 'b, 'c, R, K, V>
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'b mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'b mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut())
+        }
+        self
+    }
+}
+
+pub struct OccupiedEntry<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> {
+    cursor: Cursor<'a, R, K, V>,
+    lock: &'b Lock<'c>,
+}
+
+impl<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod>
+    OccupiedEntry<'a, 'b, 'c, R, K, V>
+{
+    pub fn key(&self) -> &K {
+        self.cursor.key(self.lock).unwrap()
+    }
+
+    pub fn get(&self) -> &V {
+        self.cursor.value(self.lock).unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.cursor.value_mut(self.lock).unwrap() }
+    }
+
+    pub fn into_mut(mut self) -> &'b mut V {
+        unsafe { self.cursor.value_mut(self.lock).unwrap() }
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let entry = (*self.key(), *self.get());
+        unsafe { self.cursor.delete(self.lock) };
+        entry
+    }
 }
 
 pub struct VacantEntry<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod> {
@@ -160,6 +314,14 @@ pub struct VacantEntry<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Or
 impl<'a, 'b: 'a, 'c, R: DerefMut<Target = PageNr>, K: Pod + Ord, V: Pod>
     VacantEntry<'a, 'b, 'c, R, K, V>
 {
+    pub fn key(&self) -> &'a K {
+        self.cursor.missing_key().unwrap()
+    }
+
+    pub fn into_key(self) -> K {
+        *self.key()
+    }
+
     pub fn insert(mut self, value: V) -> &'b mut V {
         unsafe {
             self.cursor.set_value(value, self.lock);
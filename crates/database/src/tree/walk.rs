@@ -0,0 +1,140 @@
+use bytemuck::{Pod, TransparentWrapper};
+
+use crate::{
+    check::{Checker, Problem},
+    compact::Copier,
+    page::{PageNr, NULL_PAGE_NR},
+};
+
+use super::{
+    branch::Branch,
+    leaf::Leaf,
+    node::{node, NodePage, NodeRef},
+};
+
+struct Node<'a, K> {
+    page_nr: PageNr,
+    depth: usize,
+    lower: Option<&'a K>,
+    upper: Option<&'a K>,
+}
+
+impl<'a, K: Ord> Node<'a, K> {
+    fn is_root(&self) -> bool {
+        self.depth == 0
+    }
+
+    fn is_ordered(&self, keys: &[K]) -> bool {
+        keys.windows(2).all(|pair| pair[0] < pair[1])
+            && keys.first().map_or(true, |key| self.lower <= Some(key))
+            && keys
+                .last()
+                .map_or(true, |key| self.upper.map_or(true, |upper| key < upper))
+    }
+}
+
+pub(crate) fn check<K: Pod + Ord, V: Pod, F: FnMut(&mut Checker, &V)>(
+    root: PageNr,
+    checker: &mut Checker,
+    value: &mut F,
+) {
+    if root == NULL_PAGE_NR {
+        return;
+    }
+    let root = Node {
+        page_nr: root,
+        depth: 0,
+        lower: None,
+        upper: None,
+    };
+    check_node::<K, V, F>(root, &mut None, checker, value)
+}
+
+fn check_node<K: Pod + Ord, V: Pod, F: FnMut(&mut Checker, &V)>(
+    this: Node<K>,
+    leaf_depth: &mut Option<usize>,
+    checker: &mut Checker,
+    value: &mut F,
+) {
+    if !checker.reference(this.page_nr) {
+        return;
+    }
+    let page = unsafe { checker.lock().page(this.page_nr) };
+    match node::<K, V>(page) {
+        NodeRef::Leaf(leaf) => {
+            let order = Leaf::<K, V>::order();
+            if *leaf_depth.get_or_insert(this.depth) != this.depth {
+                checker.problem(Problem::Unbalanced(this.page_nr));
+            }
+            if leaf.len() > order {
+                checker.problem(Problem::Overfilled(this.page_nr));
+                return;
+            }
+            if leaf.len() == 0 || (!this.is_root() && leaf.len() < order / 2) {
+                checker.problem(Problem::Underfilled(this.page_nr));
+            }
+            if !this.is_ordered(leaf.keys()) {
+                checker.problem(Problem::Unordered(this.page_nr));
+            }
+            for v in leaf.values() {
+                value(checker, v);
+            }
+        }
+        NodeRef::Branch(branch) => {
+            let order = Branch::<K>::order();
+            if branch.len() > order {
+                checker.problem(Problem::Overfilled(this.page_nr));
+                return;
+            }
+            if branch.len() < 2 {
+                checker.problem(Problem::Underfilled(this.page_nr));
+                return;
+            }
+            if !this.is_root() && branch.len() < order / 2 {
+                checker.problem(Problem::Underfilled(this.page_nr));
+            }
+            let keys = branch.keys();
+            if !this.is_ordered(keys) {
+                checker.problem(Problem::Unordered(this.page_nr));
+            }
+            for (index, child) in branch.children().iter().enumerate() {
+                if *child == NULL_PAGE_NR {
+                    checker.problem(Problem::Missing(this.page_nr));
+                    continue;
+                }
+                let child = Node {
+                    page_nr: *child,
+                    depth: this.depth + 1,
+                    lower: if index == 0 {
+                        this.lower
+                    } else {
+                        Some(&keys[index - 1])
+                    },
+                    upper: keys.get(index).or(this.upper),
+                };
+                check_node::<K, V, F>(child, leaf_depth, checker, value);
+            }
+        }
+    }
+}
+
+pub(crate) fn copy<K: Pod + Ord, V: Pod, F: FnMut(&mut Copier, &mut V)>(
+    page_nr: PageNr,
+    copier: &mut Copier,
+    value: &mut F,
+) -> PageNr {
+    if page_nr == NULL_PAGE_NR {
+        return NULL_PAGE_NR;
+    }
+    let (new_nr, page) = copier.copy_page(page_nr);
+    if page.is_leaf() {
+        for v in Leaf::<K, V>::wrap_mut(page).values_mut() {
+            value(copier, v);
+        }
+    } else {
+        for child in Branch::<K>::wrap_mut(page).children_mut() {
+            *child = copy::<K, V, F>(*child, copier, value);
+        }
+    }
+    new_nr
+}
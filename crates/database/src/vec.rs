@@ -1,15 +1,19 @@
 use std::{
     cell::Cell,
+    cmp::Ordering,
     intrinsics::transmute,
     io::{self, Read, Write},
     marker::PhantomData,
     mem::size_of,
-    ops::{Deref, DerefMut, Index, IndexMut},
+    ops::{Deref, DerefMut, Index, IndexMut, Range},
 };
 
 use bytemuck::{cast_slice, cast_slice_mut, Pod};
 
 use crate::{
+    check::Checker,
+    compact::Copier,
+    container::{Container, Walk},
     cursor::{reallocate, PageLookup},
     lock::Lock,
     page::{PageNr, PAGE_SIZE},
@@ -20,12 +24,12 @@ use crate::{
 #[repr(C)]
 pub struct VecHeader {
     root: PageNr,
-    len: usize,
+    len: u64,
 }
 
 impl VecHeader {
     fn pages<T>(&self) -> usize {
-        (self.len + elements_per_page::<T>() - 1) / elements_per_page::<T>()
+        (self.len as usize + elements_per_page::<T>() - 1) / elements_per_page::<T>()
     }
 }
 
@@ -79,10 +83,11 @@ impl<T: Pod> Vec<T> {
     pub fn deserialize(reader: &mut impl Read, database: DatabaseRef) -> io::Result<Self> {
         let mut bytes = [0; 4];
         reader.read_exact(&mut bytes)?;
-        let root = u32::from_ne_bytes(bytes);
+        let root = u32::from_le_bytes(bytes);
         let mut bytes = [0; 8];
         reader.read_exact(&mut bytes)?;
-        let len = u64::from_ne_bytes(bytes) as usize;
+        let len = u64::from_le_bytes(bytes);
+        let root = database.register(Container::new::<Self>(root, len));
         Ok(Self {
             header: VecHeader { root, len },
             database,
@@ -91,12 +96,24 @@ impl<T: Pod> Vec<T> {
     }
 
     pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
-        writer.write_all(&self.header.root.to_ne_bytes())?;
-        writer.write_all(&(self.header.len as u64).to_ne_bytes())?;
+        writer.write_all(&self.header.root.to_le_bytes())?;
+        writer.write_all(&self.header.len.to_le_bytes())?;
         Ok(())
     }
 }
 
+impl<T: Pod> Walk for Vec<T> {
+    fn check(checker: &mut Checker, root: PageNr, len: u64) {
+        let header = VecHeader { root, len };
+        checker.visit_pages(root, header.pages::<T>())
+    }
+
+    fn copy(copier: &mut Copier, root: PageNr, len: u64) -> PageNr {
+        let header = VecHeader { root, len };
+        copier.copy_pages(root, header.pages::<T>())
+    }
+}
+
 impl<T: Pod> Drop for Vec<T> {
     fn drop(&mut self) {
         let lock = self.database.lock();
@@ -114,67 +131,223 @@ impl<'a, T: Pod, H: Deref<Target = VecHeader>> VecGuard<'a, T, H> {
             pos: 0,
         }
     }
+
+    pub fn chunks(&self) -> Chunks<'_, 'a, T, H> {
+        Chunks {
+            guard: self,
+            page_index: 0,
+        }
+    }
+
+    pub fn copy_to_slice(&self, range: Range<usize>, dest: &mut [T]) {
+        assert!(range.start <= range.end && range.end <= self.len());
+        assert_eq!(range.len(), dest.len());
+        let mut index = range.start;
+        while index < range.end {
+            let offset = index % elements_per_page::<T>();
+            let n = (range.end - index).min(elements_per_page::<T>() - offset);
+            let pos = index - range.start;
+            dest[pos..pos + n].copy_from_slice(
+                &self.page_slice(index / elements_per_page::<T>())[offset..offset + n],
+            );
+            index += n;
+        }
+    }
+
+    fn page_slice(&self, page_index: usize) -> &[T] {
+        let start = page_index * elements_per_page::<T>();
+        let len = (self.len() - start).min(elements_per_page::<T>());
+        let pages = self.header.pages::<T>();
+        let mut lookup = self.lookup.get();
+        let page = lookup.get(self.header.root, pages, page_index, &self.lock);
+        self.lookup.set(lookup);
+        cast_slice(&page[..len * size_of::<T>()])
+    }
+
+    pub fn binary_search_by(&self, mut f: impl FnMut(&T) -> Ordering) -> Result<usize, usize> {
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self[mid]) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    pub fn binary_search(&self, value: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        self.binary_search_by(|probe| probe.cmp(value))
+    }
 }
 
 impl<'a, T: Pod, H: Deref<Target = VecHeader>> Len for VecGuard<'a, T, H> {
     fn len(&self) -> usize {
-        self.header.len
+        self.header.len as usize
     }
 }
 
 impl<'a, T: Pod, H: DerefMut<Target = VecHeader>> VecGuard<'a, T, H> {
     fn internal_resize(&mut self, new_len: usize) {
         let current_pages = self.header.pages::<T>();
-        self.header.len = new_len;
+        self.header.len = new_len as u64;
         let new_pages = self.header.pages::<T>();
         reallocate(&mut self.header.root, current_pages, new_pages, &self.lock)
     }
 
+    pub fn iter_mut(&mut self) -> IterMut<'_, Self> {
+        IterMut {
+            container: self,
+            pos: 0,
+        }
+    }
+
     pub fn resize(&mut self, new_len: usize, value: T) {
-        let old_len = self.header.len;
+        let old_len = self.len();
         self.internal_resize(new_len);
-        let mut i = old_len;
-        while i != new_len {
-            self[i] = value;
-            i += 1;
+        for index in old_len..new_len {
+            self[index] = value;
+        }
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.internal_resize(len)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.internal_resize(0)
+    }
+
+    pub fn fill(&mut self, value: T) {
+        for index in 0..self.len() {
+            self[index] = value;
         }
     }
 
     pub fn push(&mut self, value: T) {
-        let index = self.header.len;
+        let index = self.len();
         self.internal_resize(index + 1);
         self[index] = value;
     }
 
     pub fn append(&mut self, values: &[T]) {
-        let mut index = self.header.len;
+        let index = self.len();
         self.internal_resize(index + values.len());
-        for value in values {
-            self[index] = *value;
-            index += 1;
+        self.copy_from_slice(index..index + values.len(), values);
+    }
+
+    pub fn copy_from_slice(&mut self, range: Range<usize>, src: &[T]) {
+        assert!(range.start <= range.end && range.end <= self.len());
+        assert_eq!(range.len(), src.len());
+        let mut index = range.start;
+        while index < range.end {
+            let offset = index % elements_per_page::<T>();
+            let n = (range.end - index).min(elements_per_page::<T>() - offset);
+            let pos = index - range.start;
+            self.page_slice_mut(index / elements_per_page::<T>())[offset..offset + n]
+                .copy_from_slice(&src[pos..pos + n]);
+            index += n;
         }
     }
 
+    fn page_slice_mut(&mut self, page_index: usize) -> &mut [T] {
+        let start = page_index * elements_per_page::<T>();
+        let len = (self.len() - start).min(elements_per_page::<T>());
+        let pages = self.header.pages::<T>();
+        let page = unsafe {
+            self.lookup
+                .get_mut()
+                .get_mut(&mut self.header.root, pages, page_index, &self.lock)
+                .as_mut()
+                .unwrap()
+        };
+        cast_slice_mut(&mut page[..len * size_of::<T>()])
+    }
+
     pub fn pop(&mut self) -> Option<T> {
-        if self.header.len > 0 {
-            let value = self[self.header.len - 1];
-            self.internal_resize(self.header.len - 1);
+        let len = self.len();
+        if len > 0 {
+            let value = self[len - 1];
+            self.internal_resize(len - 1);
             Some(value)
         } else {
             None
         }
     }
 
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len();
+        assert!(index <= len);
+        self.internal_resize(len + 1);
+        let mut carry = value;
+        for page_index in index / elements_per_page::<T>()..self.header.pages::<T>() {
+            let start = index.saturating_sub(page_index * elements_per_page::<T>());
+            let slice = self.page_slice_mut(page_index);
+            let last = slice.len() - 1;
+            let next = slice[last];
+            slice.copy_within(start..last, start + 1);
+            slice[start] = carry;
+            carry = next;
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len);
+        let mut carry = self[len - 1];
+        for page_index in (index / elements_per_page::<T>()..self.header.pages::<T>()).rev() {
+            let start = index.saturating_sub(page_index * elements_per_page::<T>());
+            let slice = self.page_slice_mut(page_index);
+            let last = slice.len() - 1;
+            let next = slice[start];
+            slice.copy_within(start + 1.., start);
+            slice[last] = carry;
+            carry = next;
+        }
+        self.internal_resize(len - 1);
+        carry
+    }
+
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len);
+        let value = self[index];
+        self.copy_within(len - 1, index);
+        self.internal_resize(len - 1);
+        value
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) {
+        let value = self[a];
+        self[a] = self[b];
+        self[b] = value;
+    }
+
     pub fn copy_within(&mut self, src: usize, dest: usize) {
         self[dest] = self[src]
     }
 }
 
+impl<'a, T: Pod, H: DerefMut<Target = VecHeader>> Extend<T> for VecGuard<'a, T, H> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value)
+        }
+    }
+}
+
 impl<'a, T: Pod, H: Deref<Target = VecHeader>> Index<usize> for VecGuard<'a, T, H> {
     type Output = T;
 
     fn index(&self, index: usize) -> &Self::Output {
-        assert!(index < self.header.len);
+        assert!(index < self.len());
         let page_index = index / elements_per_page::<T>();
         let page_offset = index % elements_per_page::<T>() * size_of::<T>();
         let pages = self.header.pages::<T>();
@@ -187,7 +360,7 @@ impl<'a, T: Pod, H: Deref<Target = VecHeader>> Index<usize> for VecGuard<'a, T,
 
 impl<'a, T: Pod, H: DerefMut<Target = VecHeader>> IndexMut<usize> for VecGuard<'a, T, H> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        assert!(index < self.header.len);
+        assert!(index < self.len());
         let page_index = index / elements_per_page::<T>();
         let page_offset = index % elements_per_page::<T>() * size_of::<T>();
         let pages = self.header.pages::<T>();
@@ -210,12 +383,31 @@ pub trait Len {
     }
 }
 
+pub struct Chunks<'a, 'b, T: Pod, H> {
+    guard: &'a VecGuard<'b, T, H>,
+    page_index: usize,
+}
+
+impl<'a, 'b, T: Pod, H: Deref<Target = VecHeader>> Iterator for Chunks<'a, 'b, T, H> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page_index < self.guard.header.pages::<T>() {
+            let chunk = self.guard.page_slice(self.page_index);
+            self.page_index += 1;
+            Some(chunk)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct Iter<'a, T> {
     container: &'a T,
     pos: usize,
 }
 
-struct IterMut<'a, T> {
+pub struct IterMut<'a, T> {
     container: &'a mut T,
     pos: usize,
 }
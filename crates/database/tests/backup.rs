@@ -0,0 +1,31 @@
+mod common;
+
+use database::{Database, ReadOnly};
+
+use common::{assert_consistent, create, State};
+
+#[test]
+fn backup_while_open_for_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("source.db");
+    let destination = dir.path().join("backup.db");
+    let (mut database, mut state) = create(&path);
+    {
+        let mut entries = state.entries.write();
+        for i in 0..5_000 {
+            entries.insert(i, i);
+        }
+    }
+    database.snapshot(&mut state).unwrap();
+    state.entries.write().insert(5_000, 5_000);
+    Database::backup::<State>(&path, &destination).unwrap();
+    let copy: ReadOnly<State> = Database::open_read_only(&destination).unwrap();
+    assert!(copy
+        .entries
+        .read()
+        .iter()
+        .map(|(key, value)| (*key, *value))
+        .eq((0..5_000).map(|i| (i, i))));
+    drop(copy);
+    assert_consistent(&destination);
+}
@@ -0,0 +1,91 @@
+use std::{collections::BTreeMap, io::ErrorKind, path::Path};
+
+use database::{BlobTree, Database, DatabaseRef, MAX_BLOB_KEY_LEN};
+use derive::Object;
+
+#[derive(Object)]
+#[object(application = "blob-test", version = 1)]
+struct Blobs {
+    blobs: BlobTree,
+}
+
+fn create(path: &Path) -> (Database, Blobs) {
+    Database::create(path, |database: DatabaseRef| Blobs {
+        blobs: BlobTree::new(database),
+    })
+    .unwrap()
+}
+
+fn value(i: usize) -> Vec<u8> {
+    (0..i * 97 % 20_000).map(|j| (i + j) as u8).collect()
+}
+
+#[test]
+fn insert_get_remove() {
+    let directory = tempfile::tempdir().unwrap();
+    let (_database, mut blobs) = create(&directory.path().join("blobs.db"));
+    let mut blobs = blobs.blobs.write();
+    assert_eq!(blobs.insert(b"", b"empty").unwrap(), None);
+    assert_eq!(blobs.insert(b"a", &value(3)).unwrap(), None);
+    assert_eq!(blobs.insert(b"a", &value(200)).unwrap(), Some(value(3)));
+    assert_eq!(blobs.get(b"a"), Some(value(200)));
+    assert_eq!(blobs.get(b""), Some(b"empty".to_vec()));
+    assert_eq!(blobs.remove(b"a"), Some(value(200)));
+    assert_eq!(blobs.remove(b"a"), None);
+    assert!(!blobs.contains_key(b"a"));
+}
+
+#[test]
+fn rejects_long_keys() {
+    let directory = tempfile::tempdir().unwrap();
+    let (_database, mut blobs) = create(&directory.path().join("blobs.db"));
+    let mut blobs = blobs.blobs.write();
+    let key = vec![7; MAX_BLOB_KEY_LEN];
+    assert_eq!(blobs.insert(&key, b"fits").unwrap(), None);
+    let error = blobs
+        .insert(&[7; MAX_BLOB_KEY_LEN + 1], b"too long")
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(blobs.iter().count(), 1);
+    assert_eq!(blobs.get(&[7; MAX_BLOB_KEY_LEN + 1]), None);
+}
+
+#[test]
+fn ranges_and_round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("blobs.db");
+    let mut expected = BTreeMap::new();
+    {
+        let (mut database, mut blobs) = create(&path);
+        for i in 0..500 {
+            let key = format!("key-{:05}", i * 7 % 500).into_bytes();
+            blobs.blobs.write().insert(&key, &value(i)).unwrap();
+            expected.insert(key, value(i));
+        }
+        for i in (0..500).step_by(3) {
+            let key = format!("key-{:05}", i).into_bytes();
+            blobs.blobs.write().remove(&key);
+            expected.remove(&key);
+        }
+        database.snapshot(&mut blobs).unwrap();
+    }
+    let report = Database::check::<Blobs>(&path).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.problems);
+    let (_database, blobs): (_, Blobs) = Database::open(&path).unwrap();
+    let blobs = blobs.blobs.read();
+    assert!(blobs
+        .iter()
+        .map(|(key, value)| (key.to_vec(), value))
+        .eq(expected.clone().into_iter()));
+    let start = b"key-00100".to_vec();
+    let end = b"key-00200".to_vec();
+    assert!(blobs
+        .range(&start[..]..&end[..])
+        .rev()
+        .map(|(key, value)| (key.to_vec(), value))
+        .eq(expected
+            .range(start.clone()..end.clone())
+            .rev()
+            .map(|(key, value)| (key.clone(), value.clone()))));
+    assert_eq!(blobs.range(&b"key"[..]..).count(), expected.len());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::io::ErrorKind;
+
+use database::Database;
+
+use common::{assert_consistent, create, State};
+
+#[test]
+fn bulk_load_is_consistent() {
+    for (len, fill) in [(0, 1.0), (1, 1.0), (511, 1.0), (512, 1.0), (100_000, 0.7)] {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("entries.db");
+        {
+            let (mut database, mut entries) = create(&path);
+            entries.entries.write().insert(u64::MAX, 0);
+            entries
+                .entries
+                .write()
+                .bulk_load((0..len).map(|key| (key, key + 1)), fill)
+                .unwrap();
+            let tree = entries.entries.read();
+            assert!(tree
+                .iter()
+                .map(|(key, value)| (*key, *value))
+                .eq((0..len).map(|key| (key, key + 1))));
+            drop(tree);
+            entries.entries.write().insert(len / 2, 0);
+            database.snapshot(&mut entries).unwrap();
+        }
+        let report = Database::check::<State>(&path).unwrap();
+        assert!(report.is_consistent(), "{}: {:?}", len, report.problems);
+    }
+}
+
+#[test]
+fn invalid_input_keeps_the_tree() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("entries.db");
+    {
+        let (mut database, mut entries) = create(&path);
+        for key in 0..1000 {
+            entries.entries.write().insert(key, key);
+        }
+        let mut tree = entries.entries.write();
+        let error = tree
+            .bulk_load((0..10).map(|key| (key, key)), 0.0)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        let unordered = (0..5000).chain(10..20).map(|key| (key, key));
+        let error = tree.bulk_load(unordered, 1.0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(tree.iter().map(|(key, _)| *key).eq(0..1000));
+        drop(tree);
+        database.snapshot(&mut entries).unwrap();
+    }
+    assert_consistent(&path);
+}
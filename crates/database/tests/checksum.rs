@@ -0,0 +1,89 @@
+use std::{
+    convert::TryInto,
+    fs, io,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use database::{Database, DatabaseRef, Vec};
+use derive::Object;
+
+const PAGE_SIZE: usize = 8192;
+
+const MARKER: u64 = 0x5eed_0000_0000_0000;
+
+#[derive(Object)]
+#[object(application = "checksum-test", version = 1)]
+struct State {
+    values: Vec<u64>,
+}
+
+fn create_corrupted(path: &Path) -> usize {
+    {
+        let (mut database, mut state) =
+            Database::create_with_checksums(path, |database: DatabaseRef| State {
+                values: Vec::new(database),
+            })
+            .unwrap();
+        let values: std::vec::Vec<u64> = (0..10_000).map(|i| MARKER | i).collect();
+        state.values.write().append(&values);
+        database.snapshot(&mut state).unwrap();
+    }
+    let mut bytes = fs::read(path).unwrap();
+    let offset = (0..bytes.len())
+        .step_by(PAGE_SIZE)
+        .find(|offset| {
+            u64::from_le_bytes(bytes[*offset..*offset + 8].try_into().unwrap()) == MARKER
+        })
+        .unwrap();
+    bytes[offset + 8] ^= 1;
+    fs::write(path, bytes).unwrap();
+    offset / PAGE_SIZE
+}
+
+#[test]
+fn reading_a_corrupted_page_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checksum.db");
+    let nr = create_corrupted(&path);
+    let (database, state): (_, State) = Database::open(&path).unwrap();
+    database.set_verify(true);
+    let panic = catch_unwind(AssertUnwindSafe(|| state.values.read()[1])).unwrap_err();
+    assert_eq!(
+        panic.downcast_ref::<String>().unwrap(),
+        &format!("checksum mismatch in page {}", nr)
+    );
+}
+
+#[test]
+fn snapshot_reports_a_corrupted_page_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checksum.db");
+    let nr = create_corrupted(&path);
+    let (mut database, mut state): (_, State) = Database::open(&path).unwrap();
+    database.set_verify(true);
+    assert!(catch_unwind(AssertUnwindSafe(|| state.values.read()[1])).is_err());
+    let error = database.snapshot(&mut state).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        error.to_string(),
+        format!("checksum mismatch in page {}", nr)
+    );
+    database.snapshot(&mut state).unwrap();
+}
+
+#[test]
+fn snapshot_verifies_copied_pages_without_read_verification() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checksum.db");
+    let nr = create_corrupted(&path);
+    let (mut database, mut state): (_, State) = Database::open(&path).unwrap();
+    database.set_verify(false);
+    assert_eq!(state.values.read()[1], MARKER);
+    state.values.write()[2] = 0;
+    let error = database.snapshot(&mut state).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("checksum mismatch in page {}", nr)
+    );
+}
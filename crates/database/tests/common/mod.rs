@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use database::{Database, DatabaseRef, Tree, Vec};
+use derive::Object;
+
+#[derive(Object)]
+#[object(application = "database-test", version = 1)]
+pub struct State {
+    pub entries: Tree<u64, u64>,
+    pub values: Vec<u64>,
+}
+
+impl State {
+    pub fn new(database: DatabaseRef) -> Self {
+        Self {
+            entries: Tree::new(database.clone()),
+            values: Vec::new(database),
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub fn create(path: &Path) -> (Database, State) {
+    Database::create(path, State::new).unwrap()
+}
+
+#[allow(dead_code)]
+pub fn in_memory() -> (Database, State) {
+    Database::in_memory(State::new).unwrap()
+}
+
+#[allow(dead_code)]
+pub fn assert_consistent(path: &Path) {
+    let report = Database::check::<State>(path).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.problems);
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::fs::metadata;
+
+use database::Database;
+
+use common::{create, State};
+
+#[test]
+fn compact_shrinks_and_preserves_content() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("state.db");
+    {
+        let (mut database, mut state) = create(&path);
+        for i in 0..100_000 {
+            state.entries.write().insert(i, i * 3);
+        }
+        state
+            .values
+            .write()
+            .append(&(0..200_000).collect::<std::vec::Vec<_>>());
+        database.snapshot(&mut state).unwrap();
+        for i in 0..99_000 {
+            state.entries.write().remove(&i);
+        }
+        state.values.write().truncate(10);
+        database.snapshot(&mut state).unwrap();
+        database.snapshot(&mut state).unwrap();
+    }
+    let before = metadata(&path).unwrap().len();
+    Database::compact::<State>(&path).unwrap();
+    let after = metadata(&path).unwrap().len();
+    assert!(after < before / 4, "{} -> {}", before, after);
+    let report = Database::check::<State>(&path).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.problems);
+    assert_eq!(report.free, 0);
+    let (_database, state): (_, State) = Database::open(&path).unwrap();
+    assert!(state
+        .entries
+        .read()
+        .iter()
+        .map(|(key, value)| (*key, *value))
+        .eq((99_000..100_000).map(|i| (i, i * 3))));
+    assert!(state.values.read().iter().copied().eq(0..10));
+}
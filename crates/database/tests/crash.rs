@@ -0,0 +1,190 @@
+//! Needs the `fault-injection` feature: `cargo test -p database --features fault-injection`.
+
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use database::{Database, DatabaseRef, Faults, FaultyStorage, Tree, Vec};
+use derive::Object;
+
+const ROUNDS: u64 = 64;
+
+const OPERATIONS: usize = 256;
+
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+#[derive(Object)]
+#[object(application = "crash-test", version = 1)]
+struct Model {
+    entries: Tree<u64, u64>,
+    values: Vec<u64>,
+}
+
+impl Model {
+    fn new(database: DatabaseRef) -> Self {
+        Self {
+            entries: Tree::new(database.clone()),
+            values: Vec::new(database),
+        }
+    }
+
+    fn state(&self) -> State {
+        State {
+            entries: self
+                .entries
+                .read()
+                .iter()
+                .map(|(key, value)| (*key, *value))
+                .collect(),
+            values: self.values.read().iter().copied().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct State {
+    entries: BTreeMap<u64, u64>,
+    values: std::vec::Vec<u64>,
+}
+
+impl State {
+    fn mutate(&mut self, model: &mut Model, rng: &mut Rng) {
+        match rng.below(5) {
+            0 | 1 => {
+                let key = rng.below(4096) as u64;
+                let value = rng.next();
+                model.entries.write().insert(key, value);
+                self.entries.insert(key, value);
+            }
+            2 => {
+                let key = rng.below(4096) as u64;
+                model.entries.write().remove(&key);
+                self.entries.remove(&key);
+            }
+            3 => {
+                let len = rng.below(256);
+                let values: std::vec::Vec<u64> = (0..len).map(|_| rng.next()).collect();
+                model.values.write().append(&values);
+                self.values.extend_from_slice(&values);
+            }
+            _ => {
+                let len = self.values.len() - rng.below(self.values.len() / 4 + 1);
+                model.values.write().truncate(len);
+                self.values.truncate(len);
+            }
+        }
+    }
+}
+
+fn seed() -> u64 {
+    env::var("CRASH_TEST_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as u64
+        })
+}
+
+fn round(seed: u64, checksums: bool, path: &Path) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let storage = FaultyStorage::new(Faults::default(), !seed).map_err(|e| e.to_string())?;
+    let (mut database, mut model) = storage
+        .create(checksums, Model::new)
+        .map_err(|e| e.to_string())?;
+    let mut state = State::default();
+    let mut committed = std::vec::Vec::new();
+    let mut pending = None;
+    for _ in 0..rng.below(OPERATIONS + 1) {
+        state.mutate(&mut model, &mut rng);
+        if rng.chance(0.125) {
+            let result = if rng.chance(0.5) {
+                database.snapshot(&mut model)
+            } else {
+                database.checkpoint(&mut model).map(|_| ())
+            };
+            match result {
+                Ok(()) => committed.push(state.clone()),
+                Err(_) => {
+                    pending = Some(state.clone());
+                    break;
+                }
+            }
+        }
+    }
+    let image = storage.crash();
+    drop(model);
+    drop(database);
+    fs::write(path, image).map_err(|e| e.to_string())?;
+    let recovered: io::Result<(Database, Model)> = Database::open(path);
+    let recovered = recovered.map(|(_, model)| model.state());
+    if committed.is_empty() && (pending.is_none() || recovered.is_err()) {
+        return Ok(());
+    }
+    let recovered = recovered.map_err(|e| e.to_string())?;
+    let check = Database::check::<Model>(path).map_err(|e| e.to_string())?;
+    if !check.is_consistent() {
+        return Err(format!(
+            "recovered version {} has problems: {:?}",
+            check.version,
+            check
+                .problems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<std::vec::Vec<_>>()
+        ));
+    }
+    if committed.contains(&recovered) || pending.as_ref() == Some(&recovered) {
+        Ok(())
+    } else {
+        Err("recovered state does not match any committed snapshot".into())
+    }
+}
+
+fn crash_rounds(checksums: bool) {
+    let seed = seed();
+    let dir = tempfile::tempdir().unwrap();
+    for offset in 0..ROUNDS {
+        let seed = seed.wrapping_add(offset);
+        let path = dir.path().join(format!("{}.db", offset));
+        if let Err(error) = round(seed, checksums, &path) {
+            panic!("crash round failed (CRASH_TEST_SEED={}): {}", seed, error);
+        }
+    }
+}
+
+#[test]
+fn recovers_a_committed_snapshot_after_crashes() {
+    crash_rounds(false)
+}
+
+#[test]
+fn recovers_a_committed_snapshot_after_crashes_with_checksums() {
+    crash_rounds(true)
+}
@@ -0,0 +1,130 @@
+use std::mem::forget;
+
+use database::{Database, DatabaseRef, Vec};
+use derive::{DbVec, Object};
+
+#[derive(DbVec)]
+pub struct Item {
+    pub value: u64,
+    pub weight: u32,
+}
+
+#[derive(Object)]
+#[object(application = "db-vec-test", version = 1)]
+struct State {
+    items: ItemVec,
+}
+
+fn create() -> (Database, State) {
+    Database::in_memory(|database: DatabaseRef| State {
+        items: ItemVec::new(database),
+    })
+    .unwrap()
+}
+
+fn item(value: u64) -> Item {
+    Item {
+        value,
+        weight: value as u32 + 1,
+    }
+}
+
+#[test]
+fn stale_handle_is_rejected() {
+    let (_database, mut state) = create();
+    let items = &mut state.items;
+    let stale = items.add(item(1));
+    assert!(items.free(stale));
+    let fresh = items.add(item(2));
+    assert_eq!(fresh.index, stale.index);
+    assert!(!items.contains(stale));
+    assert!(items.get(stale).is_none());
+    assert!(!items.set(stale, item(3)));
+    assert!(!items.free(stale));
+    assert_eq!(items.get(fresh).unwrap().value, 2);
+}
+
+#[test]
+fn freed_slot_is_reused_with_a_new_generation() {
+    let (_database, mut state) = create();
+    let items = &mut state.items;
+    let handles: std::vec::Vec<_> = (0..4).map(|value| items.add(item(value))).collect();
+    assert!(handles.iter().all(|handle| handle.generation == 1));
+    assert!(items.free(handles[2]));
+    assert_eq!(items.handle(2), None);
+    let reused = items.add(item(7));
+    assert_eq!(reused.index, 2);
+    assert_eq!(reused.generation, 3);
+    assert_eq!(items.handle(2), Some(reused));
+    let value = items.get(reused).unwrap();
+    assert_eq!((value.value, value.weight), (7, 8));
+    assert_eq!(items.add(item(9)).index, 4);
+}
+
+#[test]
+fn iter_used_skips_free_slots() {
+    let (_database, mut state) = create();
+    let items = &mut state.items;
+    let handles: std::vec::Vec<_> = (0..6).map(|value| items.add(item(value))).collect();
+    assert!(items.free(handles[0]));
+    assert!(items.free(handles[3]));
+    assert!(items.free(handles[5]));
+    assert_eq!(items.len_used(), 3);
+    assert!(items
+        .iter_used()
+        .eq([handles[1], handles[2], handles[4]].iter().copied()));
+    assert!(items.set(handles[4], item(40)));
+    let values: std::vec::Vec<u64> = items
+        .iter_used()
+        .map(|handle| items.get(handle).unwrap().value)
+        .collect();
+    assert_eq!(values, [1, 2, 40]);
+}
+
+#[test]
+fn deserialize_legacy_round_trip() {
+    let mut reference = None;
+    let (_database, _state) = Database::in_memory(|database: DatabaseRef| {
+        reference = Some(database.clone());
+        State {
+            items: ItemVec::new(database),
+        }
+    })
+    .unwrap();
+    let database = reference.unwrap();
+    let mut value = Vec::<u64>::new(database.clone());
+    let mut weight = Vec::<u32>::new(database.clone());
+    let mut free = Vec::<u64>::new(database.clone());
+    let mut used = Vec::<u8>::new(database.clone());
+    value.write().append(&[10, 20, 30]);
+    weight.write().append(&[1, 2, 3]);
+    free.write().push(1);
+    used.write().append(&[1, 0, 1]);
+    let mut bytes = std::vec::Vec::new();
+    value.serialize(&mut bytes).unwrap();
+    weight.serialize(&mut bytes).unwrap();
+    free.serialize(&mut bytes).unwrap();
+    used.serialize(&mut bytes).unwrap();
+    // The legacy vec takes over the pages.
+    forget((value, weight, free, used));
+    let mut items = ItemVec::deserialize_legacy(&bytes[..], database).unwrap();
+    assert_eq!(items.len_used(), 2);
+    let handles: std::vec::Vec<_> = items.iter_used().collect();
+    assert_eq!(
+        handles,
+        [
+            ItemHandle {
+                index: 0,
+                generation: 1
+            },
+            ItemHandle {
+                index: 2,
+                generation: 1
+            }
+        ]
+    );
+    assert_eq!(items.get(handles[1]).unwrap().value, 30);
+    let reused = items.add(item(5));
+    assert_eq!((reused.index, reused.generation), (1, 1));
+    assert_eq!(items.get(reused).unwrap().value, 5);
+}
@@ -0,0 +1,108 @@
+use std::{convert::TryInto, fs};
+
+use database::{Database, DatabaseRef, DbSerialize, OneToMany, Tree, Vec};
+use derive::{DbSerialize, DbVec, Object};
+
+#[derive(DbVec)]
+pub struct Item {
+    pub value: u64,
+}
+
+#[derive(Object)]
+#[object(application = "format-test", version = 1)]
+struct State {
+    values: Vec<u32>,
+    entries: Tree<u32, u64>,
+    groups: OneToMany,
+    items: ItemVec,
+}
+
+fn create(database: DatabaseRef) -> State {
+    State {
+        values: Vec::new(database.clone()),
+        entries: Tree::new(database.clone()),
+        groups: OneToMany::new(database.clone(), 3),
+        items: ItemVec::new(database),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("format.db");
+    let freed = {
+        let (mut database, mut state) = Database::create(&path, create).unwrap();
+        state.values.write().append(&[1, 0x0102_0304, u32::MAX]);
+        {
+            let mut entries = state.entries.write();
+            for key in 0..1_000 {
+                entries.insert(key, u64::from(key) << 32 | 0xff);
+            }
+        }
+        for member in 0..30 {
+            assert!(state.groups.insert(member % 3, member));
+        }
+        assert_eq!(state.groups.remove(4), Some(1));
+        let handles: std::vec::Vec<_> = (0..10)
+            .map(|value| state.items.add(Item { value }))
+            .collect();
+        assert!(state.items.free(handles[3]));
+        database.snapshot(&mut state).unwrap();
+        handles[3]
+    };
+    let (_database, mut state): (_, State) = Database::open(&path).unwrap();
+    assert!(state
+        .values
+        .read()
+        .iter()
+        .copied()
+        .eq([1, 0x0102_0304, u32::MAX]));
+    assert!(state
+        .entries
+        .read()
+        .iter()
+        .map(|(key, value)| (*key, *value))
+        .eq((0..1_000).map(|key| (key, u64::from(key) << 32 | 0xff))));
+    assert_eq!(state.groups.group_of(4), None);
+    assert_eq!(state.groups.group_of(7), Some(1));
+    assert_eq!(state.groups.count(1), 9);
+    assert_eq!(state.items.len_used(), 9);
+    assert!(!state.items.contains(freed));
+    let reused = state.items.add(Item { value: 42 });
+    assert_eq!(reused.index, freed.index);
+    assert_eq!(state.items.get(reused).unwrap().value, 42);
+    drop(state);
+    assert!(Database::check::<State>(&path).unwrap().is_consistent());
+}
+
+#[test]
+fn header_is_little_endian() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("format.db");
+    let (mut database, mut state) = Database::create(&path, create).unwrap();
+    database.snapshot(&mut state).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    let layout = u64::from_le_bytes(bytes[256..264].try_into().unwrap());
+    let flags = u64::from_le_bytes(bytes[264..272].try_into().unwrap());
+    assert_eq!(layout, 1);
+    assert_eq!(flags & 1, 1);
+}
+
+#[derive(DbSerialize)]
+struct Named {
+    #[db(serde)]
+    name: String,
+    values: Vec<u32>,
+}
+
+#[derive(DbSerialize)]
+struct QualifiedNamed {
+    #[db(serde)]
+    name: std::string::String,
+    values: Vec<u32>,
+}
+
+#[test]
+fn serde_field_layout_ignores_type_spelling() {
+    assert_eq!(Named::LAYOUT, QualifiedNamed::LAYOUT);
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap as StdHashMap;
+
+use database::{Database, DatabaseRef, HashMap};
+use derive::Object;
+
+#[derive(Object)]
+#[object(application = "hash-map-test", version = 1)]
+struct Map {
+    map: HashMap<u64, u64>,
+}
+
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[test]
+fn matches_std_hash_map() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("map.db");
+    let mut expected = StdHashMap::new();
+    {
+        let (mut database, mut map) = Database::create(&path, |database: DatabaseRef| Map {
+            map: HashMap::new(database),
+        })
+        .unwrap();
+        let mut state = 1;
+        for _ in 0..100_000 {
+            let key = next(&mut state) % 20_000;
+            let mut map = map.map.write();
+            if next(&mut state) % 3 == 0 {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            } else {
+                let value = next(&mut state);
+                assert_eq!(map.insert(key, value), expected.insert(key, value));
+            }
+        }
+        if let Some(value) = map.map.write().get_mut(&1) {
+            *value = 7;
+            expected.insert(1, 7);
+        }
+        database.snapshot(&mut map).unwrap();
+    }
+    let report = Database::check::<Map>(&path).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.problems);
+    let (_database, mut map): (_, Map) = Database::open(&path).unwrap();
+    {
+        let map = map.map.read();
+        assert_eq!(map.iter().count(), expected.len());
+        for (key, value) in map.iter() {
+            assert_eq!(expected.get(key), Some(value));
+        }
+        for key in 0..20_000 {
+            assert_eq!(map.contains_key(&key), expected.contains_key(&key));
+        }
+    }
+    map.map.write().clear();
+    assert_eq!(map.map.read().iter().count(), 0);
+}
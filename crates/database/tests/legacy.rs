@@ -0,0 +1,88 @@
+use std::{
+    convert::TryInto,
+    fs,
+    io::{self, Read, Write},
+};
+
+use database::{Database, DatabaseRef, Len, Migrations, Object, ReadOnly, Vec};
+use derive::Object;
+use sha3::{Digest, Sha3_512};
+
+const LEGACY_ID: [u8; 256] = [7; 256];
+
+#[derive(Object)]
+#[object(
+    application = "legacy-test",
+    version = 1,
+    migrations = "migrations",
+    legacy = "LEGACY_ID"
+)]
+struct State {
+    values: Vec<u64>,
+}
+
+fn migrations() -> Migrations {
+    Migrations::new().with(0, migrate_v0)
+}
+
+fn migrate_v0(_: &mut dyn Read, writer: &mut dyn Write, database: DatabaseRef) -> io::Result<()> {
+    let mut values = Vec::new(database);
+    values.write().append(&[1, 2, 3]);
+    Object::serialize(&mut State { values }, writer)
+}
+
+fn legacy_snapshot(version: u64) -> std::vec::Vec<u8> {
+    let mut state = std::vec::Vec::new();
+    state.extend_from_slice(&version.to_le_bytes());
+    state.extend_from_slice(&[0; 24]);
+    state.extend_from_slice(&1u32.to_le_bytes());
+    state.extend_from_slice(&0u32.to_le_bytes());
+    state.extend_from_slice(&0u64.to_le_bytes());
+    let checksum: [u8; 64] = Sha3_512::digest(&state)[..].try_into().unwrap();
+    state.extend_from_slice(&checksum);
+    state
+}
+
+fn legacy_file(id: [u8; 256]) -> std::vec::Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend(legacy_snapshot(4));
+    bytes.extend(legacy_snapshot(3));
+    bytes.resize(8192, 0);
+    bytes
+}
+
+#[test]
+fn legacy_header_opens_as_version_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("legacy.db");
+    fs::write(&path, legacy_file(LEGACY_ID)).unwrap();
+    let read_only: io::Result<ReadOnly<State>> = Database::open_read_only(&path);
+    assert!(read_only.is_err());
+    {
+        let (mut database, mut state): (_, State) = Database::open(&path).unwrap();
+        assert_eq!(
+            state
+                .values
+                .read()
+                .iter()
+                .copied()
+                .collect::<std::vec::Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        database.snapshot(&mut state).unwrap();
+    }
+    let (database, state): (_, State) = Database::open(&path).unwrap();
+    assert_eq!(state.values.read().len(), 3);
+    drop(state);
+    drop(database);
+    assert!(Database::check::<State>(&path).unwrap().is_consistent());
+}
+
+#[test]
+fn unknown_legacy_header_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("legacy.db");
+    fs::write(&path, legacy_file([8; 256])).unwrap();
+    let result: io::Result<(Database, State)> = Database::open(&path);
+    assert!(result.is_err());
+}
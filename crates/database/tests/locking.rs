@@ -0,0 +1,82 @@
+mod common;
+
+use std::{io, path::Path};
+
+use database::{Database, ReadOnly};
+
+use common::{create, State};
+
+fn populated(path: &Path) -> (Database, State) {
+    let (mut database, mut state) = create(path);
+    {
+        let mut entries = state.entries.write();
+        for i in 0..10_000 {
+            entries.insert(i, i);
+        }
+    }
+    database.snapshot(&mut state).unwrap();
+    (database, state)
+}
+
+#[test]
+fn second_writer_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("locking.db");
+    let (_database, _state) = populated(&path);
+    let result: io::Result<(Database, State)> = Database::open(&path);
+    assert_eq!(result.err().unwrap().kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn readers_do_not_block_the_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("locking.db");
+    let (mut database, mut state) = populated(&path);
+    let reader: ReadOnly<State> = Database::open_read_only(&path).unwrap();
+    state.entries.write().insert(10_000, 10_000);
+    database.snapshot(&mut state).unwrap();
+    assert_eq!(reader.entries.read().get(&10_000), None);
+    drop(state);
+    drop(database);
+    let reader: ReadOnly<State> = Database::open_read_only(&path).unwrap();
+    assert_eq!(reader.entries.read().get(&10_000), Some(&10_000));
+}
+
+#[test]
+fn readers_in_other_processes_keep_their_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("locking.db");
+    let (mut database, mut state) = populated(&path);
+    let reader: ReadOnly<State> = Database::open_read_only(&path).unwrap();
+    for round in 1..5 {
+        {
+            let mut entries = state.entries.write();
+            for i in 0..10_000 {
+                entries.insert(i, i * round);
+            }
+        }
+        database.snapshot(&mut state).unwrap();
+    }
+    assert!(reader
+        .entries
+        .read()
+        .iter()
+        .map(|(key, value)| (*key, *value))
+        .eq((0..10_000).map(|i| (i, i))));
+}
+
+#[test]
+fn read_transactions_keep_the_writer_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("locking.db");
+    let (database, state) = populated(&path);
+    let reader: ReadOnly<State> = database.begin_read().unwrap();
+    drop(state);
+    drop(database);
+    let result: io::Result<(Database, State)> = Database::open(&path);
+    assert!(result.is_err());
+    assert_eq!(reader.entries.read().get(&42), Some(&42));
+    drop(reader);
+    let result: io::Result<(Database, State)> = Database::open(&path);
+    assert!(result.is_ok());
+}
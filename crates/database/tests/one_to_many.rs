@@ -0,0 +1,76 @@
+use database::{Database, DatabaseRef, OneToMany};
+use derive::Object;
+
+#[derive(Object)]
+#[object(application = "one-to-many-test", version = 1)]
+struct Index {
+    groups: OneToMany,
+}
+
+fn create(groups: usize) -> (Database, Index) {
+    Database::in_memory(|database: DatabaseRef| Index {
+        groups: OneToMany::new(database, groups),
+    })
+    .unwrap()
+}
+
+fn members(index: &OneToMany, group: usize) -> Vec<usize> {
+    let mut members: Vec<usize> = index.iter(group).collect();
+    members.sort_unstable();
+    members
+}
+
+#[test]
+fn insert_remove_and_move() {
+    let (_database, mut index) = create(3);
+    let index = &mut index.groups;
+    assert!(index.insert(0, 5));
+    assert!(index.insert(0, 2));
+    assert!(index.insert(1, 7));
+    assert_eq!(members(index, 0), vec![2, 5]);
+    assert_eq!(index.group_of(7), Some(1));
+    assert!(index.move_to(5, 2));
+    assert_eq!(members(index, 0), vec![2]);
+    assert_eq!(members(index, 2), vec![5]);
+    assert_eq!(index.remove(2), Some(0));
+    assert_eq!(index.remove(2), None);
+    assert_eq!(index.count(0), 0);
+}
+
+#[test]
+fn rejects_invalid_changes_without_mutating() {
+    let (_database, mut index) = create(2);
+    let index = &mut index.groups;
+    assert!(index.insert(0, 1));
+    assert!(!index.insert(1, 1));
+    assert!(!index.insert(2, 3));
+    assert!(!index.move_to(1, 2));
+    assert!(!index.move_to(3, 1));
+    assert_eq!(index.group_of(1), Some(0));
+    assert!(!index.contains(3));
+    assert_eq!(index.count(1), 0);
+    assert_eq!(members(index, 0), vec![1]);
+}
+
+#[test]
+fn round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("index.db");
+    {
+        let (mut database, mut index) = Database::create(&path, |database: DatabaseRef| Index {
+            groups: OneToMany::new(database, 4),
+        })
+        .unwrap();
+        for member in 0..100 {
+            assert!(index.groups.insert(member % 4, member));
+        }
+        database.snapshot(&mut index).unwrap();
+    }
+    let (_database, index): (_, Index) = Database::open(&path).unwrap();
+    for group in 0..4 {
+        assert_eq!(
+            members(&index.groups, group),
+            (group..100).step_by(4).collect::<Vec<_>>()
+        );
+    }
+}
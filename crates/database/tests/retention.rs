@@ -0,0 +1,62 @@
+mod common;
+
+use std::io;
+
+use common::{assert_consistent, create, State};
+
+#[test]
+fn open_and_restore_retained_snapshots() {
+    let directory = tempfile::tempdir().unwrap();
+    let (mut database, mut state) = create(&directory.path().join("values.db"));
+    let mut versions = Vec::new();
+    for i in 0..4 {
+        state.entries.write().insert(i, i * 10);
+        versions.push(database.checkpoint(&mut state).unwrap());
+    }
+    assert_eq!(database.list_snapshots(), versions);
+    let old = database.open_at::<State>(versions[1]).unwrap();
+    assert_eq!(old.entries.read().iter().count(), 2);
+    drop(old);
+    database.restore(&mut state, versions[1]).unwrap();
+    let entries = state.entries.read();
+    assert_eq!(entries.get(&1), Some(&10));
+    assert_eq!(entries.get(&2), None);
+}
+
+#[test]
+fn restoring_an_unknown_version_keeps_the_content() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("values.db");
+    let (mut database, mut state) = create(&path);
+    state.entries.write().insert(1, 10);
+    let version = database.checkpoint(&mut state).unwrap();
+    state.entries.write().insert(2, 20);
+    let error = database.restore(&mut state, version + 100).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(state.entries.read().get(&1), Some(&10));
+    assert_eq!(state.entries.read().get(&2), Some(&20));
+    database.snapshot(&mut state).unwrap();
+    drop(state);
+    drop(database);
+    assert_consistent(&path);
+}
+
+#[test]
+fn retention_is_clamped_and_enforced() {
+    let directory = tempfile::tempdir().unwrap();
+    let (mut database, mut state) = create(&directory.path().join("values.db"));
+    database.set_retention(usize::MAX);
+    for i in 0..40 {
+        state.entries.write().insert(i, i);
+        database.checkpoint(&mut state).unwrap();
+    }
+    assert!(database.list_snapshots().len() < 40);
+    database.set_retention(2);
+    state.entries.write().insert(100, 100);
+    let version = database.checkpoint(&mut state).unwrap();
+    let snapshots = database.list_snapshots();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots.last(), Some(&version));
+    database.release_snapshot(version).unwrap();
+    assert!(database.open_at::<State>(version).is_err());
+}
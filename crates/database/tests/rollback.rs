@@ -0,0 +1,64 @@
+mod common;
+
+use std::io;
+
+use database::Len;
+
+use common::{in_memory, State};
+
+#[test]
+fn rollback_discards_uncommitted_changes() {
+    let (mut database, mut state) = in_memory();
+    state.entries.write().insert(1, 1);
+    state.values.write().append(&[1, 2, 3]);
+    database.snapshot(&mut state).unwrap();
+    let stats = database.stats::<State>().unwrap();
+    for i in 2..1000 {
+        state.entries.write().insert(i, i);
+    }
+    state.values.write().truncate(1);
+    database.rollback(&mut state).unwrap();
+    assert_eq!(
+        state.entries.read().iter().collect::<std::vec::Vec<_>>(),
+        vec![(&1, &1)]
+    );
+    assert_eq!(
+        state
+            .values
+            .read()
+            .iter()
+            .copied()
+            .collect::<std::vec::Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        database.stats::<State>().unwrap().pages.live,
+        stats.pages.live
+    );
+}
+
+#[test]
+fn rollback_requires_a_snapshot() {
+    let (mut database, mut state) = in_memory();
+    state.entries.write().insert(1, 1);
+    state.values.write().append(&[1, 2, 3]);
+    let error = database.rollback(&mut state).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(state.entries.read().get(&1), Some(&1));
+    assert_eq!(state.values.read().len(), 3);
+    database.snapshot(&mut state).unwrap();
+    assert_eq!(state.entries.read().get(&1), Some(&1));
+}
+
+#[test]
+fn rollback_can_continue_writing() {
+    let (mut database, mut state) = in_memory();
+    database.snapshot(&mut state).unwrap();
+    state.entries.write().insert(1, 1);
+    database.rollback(&mut state).unwrap();
+    state.entries.write().insert(2, 2);
+    database.snapshot(&mut state).unwrap();
+    database.rollback(&mut state).unwrap();
+    assert_eq!(state.entries.read().get(&1), None);
+    assert_eq!(state.entries.read().get(&2), Some(&2));
+}
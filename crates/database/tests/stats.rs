@@ -0,0 +1,40 @@
+use database::{Database, DatabaseRef, OneToMany, Tree};
+use derive::Object;
+
+#[derive(Object)]
+#[object(application = "stats-test", version = 1)]
+struct State {
+    entries: Tree<u64, u64>,
+    #[db(serde)]
+    name: String,
+    groups: OneToMany,
+}
+
+#[test]
+fn stats_per_top_level_field() {
+    let (mut database, mut state) = Database::in_memory(|database: DatabaseRef| State {
+        entries: Tree::new(database.clone()),
+        name: "stats".into(),
+        groups: OneToMany::new(database, 8),
+    })
+    .unwrap();
+    {
+        let mut entries = state.entries.write();
+        for i in 0..10_000 {
+            entries.insert(i, i);
+        }
+    }
+    for member in 0..1_000 {
+        state.groups.insert(member % 8, member);
+    }
+    database.snapshot(&mut state).unwrap();
+    let stats = database.stats::<State>().unwrap();
+    let names: Vec<_> = stats.fields.0.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["entries", "groups"]);
+    let entries = stats.fields.get("entries").unwrap();
+    let groups = stats.fields.get("groups").unwrap();
+    assert!(entries.pages > groups.pages);
+    assert!(groups.pages >= 9);
+    assert_eq!(entries.bytes, entries.pages * 8192);
+    assert!(entries.pages + groups.pages < stats.pages.live);
+}
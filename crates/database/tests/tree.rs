@@ -0,0 +1,233 @@
+mod common;
+
+use std::{collections::BTreeMap, ops::DerefMut};
+
+use database::{Database, DatabaseRef, Entry, Iter, OccupiedEntry, Tree, VacantEntry};
+use derive::Object;
+
+use common::{assert_consistent, create, in_memory};
+
+fn shuffled(len: u64, seed: u64) -> Vec<u64> {
+    let mut state = seed | 1;
+    let mut keys: Vec<u64> = (0..len).collect();
+    for i in (1..keys.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        keys.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    keys
+}
+
+fn insert_and_verify(keys: &[u64]) {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("tree.db");
+    let (mut database, mut entries) = create(&path);
+    for key in keys {
+        assert_eq!(entries.entries.write().insert(*key, key * 2), None);
+    }
+    let tree = entries.entries.read();
+    let mut sorted = keys.to_vec();
+    sorted.sort_unstable();
+    assert!(tree.iter().map(|(key, _)| *key).eq(sorted.iter().copied()));
+    for key in keys {
+        assert_eq!(tree.get(key), Some(&(key * 2)));
+    }
+    drop(tree);
+    database.snapshot(&mut entries).unwrap();
+    drop(entries);
+    drop(database);
+    assert_consistent(&path);
+}
+
+#[test]
+fn insert_ascending() {
+    insert_and_verify(&(0..50_000).collect::<Vec<_>>());
+}
+
+#[test]
+fn insert_descending() {
+    insert_and_verify(&(0..50_000).rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn insert_shuffled() {
+    insert_and_verify(&shuffled(50_000, 7));
+}
+
+/// Entries per leaf of a `Tree<u64, u64>`: (PAGE_SIZE - 2) / (8 + 8).
+const LEAF_ORDER: u64 = 511;
+
+#[test]
+fn split_full_root_at_every_position() {
+    let (_database, mut entries) = in_memory();
+    let evens = (0..LEAF_ORDER).map(|i| i * 2);
+    for key in evens.clone().map(|even| even + 1) {
+        let mut tree = entries.entries.write();
+        tree.clear();
+        for even in evens.clone() {
+            tree.insert(even, even);
+        }
+        tree.insert(key, key);
+        for k in evens.clone().chain([key]) {
+            assert_eq!(tree.get(&k), Some(&k), "key {} after inserting {}", k, key);
+        }
+        assert!(tree.iter().map(|(k, _)| *k).eq({
+            let mut keys: Vec<_> = evens.clone().chain([key]).collect();
+            keys.sort_unstable();
+            keys
+        }));
+    }
+}
+
+#[test]
+fn range_queries() {
+    let directory = tempfile::tempdir().unwrap();
+    let (_database, mut entries) = create(&directory.path().join("tree.db"));
+    let mut expected = BTreeMap::new();
+    for key in shuffled(10_000, 3) {
+        entries.entries.write().insert(key * 3, key);
+        expected.insert(key * 3, key);
+    }
+    let tree = entries.entries.read();
+    for (start, end) in [(0, 0), (1, 2), (100, 4000), (29_990, 40_000)] {
+        assert!(tree.range(start..end).eq(expected.range(start..end)));
+        assert!(tree
+            .range(start..=end)
+            .rev()
+            .eq(expected.range(start..=end).rev()));
+    }
+    assert_eq!(tree.first(), expected.iter().next());
+    assert_eq!(tree.last(), expected.iter().next_back());
+}
+
+#[derive(Object)]
+#[object(application = "wide-tree-test", version = 1)]
+struct WideEntries {
+    entries: Tree<[u64; 32], u64>,
+}
+
+fn wide_key(key: u64) -> [u64; 32] {
+    let mut wide = [0; 32];
+    wide[0] = key;
+    wide
+}
+
+fn remove_and_verify(len: u64, order: &[u64]) {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("wide.db");
+    let (mut database, mut entries) =
+        Database::create(&path, |database: DatabaseRef| WideEntries {
+            entries: Tree::new(database),
+        })
+        .unwrap();
+    for key in shuffled(len, 11) {
+        entries.entries.write().insert(wide_key(key), key);
+    }
+    database.snapshot(&mut entries).unwrap();
+    drop(entries);
+    drop(database);
+    let mut remaining: BTreeMap<u64, u64> = (0..len).map(|key| (key, key)).collect();
+    for chunk in order.chunks(order.len() / 2 + 1) {
+        let (mut database, mut entries): (_, WideEntries) = Database::open(&path).unwrap();
+        for (i, key) in chunk.iter().enumerate() {
+            assert_eq!(entries.entries.write().remove(&wide_key(*key)), Some(*key));
+            remaining.remove(key);
+            if i % 97 == 0 {
+                let tree = entries.entries.read();
+                assert!(tree
+                    .iter()
+                    .map(|(key, value)| (key[0], *value))
+                    .eq(remaining.iter().map(|(key, value)| (*key, *value))));
+            }
+        }
+        database.snapshot(&mut entries).unwrap();
+        drop(entries);
+        drop(database);
+        let report = Database::check::<WideEntries>(&path).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.problems);
+    }
+    assert!(remaining.is_empty());
+}
+
+#[test]
+fn remove_ascending() {
+    remove_and_verify(5_000, &(0..5_000).collect::<Vec<_>>());
+}
+
+#[test]
+fn remove_descending() {
+    remove_and_verify(5_000, &(0..5_000).rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn remove_shuffled() {
+    remove_and_verify(5_000, &shuffled(5_000, 5));
+}
+
+#[test]
+fn entry_api() {
+    let (_database, mut entries) = in_memory();
+    let mut tree = entries.entries.write();
+    for key in 0..2000 {
+        *tree.entry(&(key % 1000)).or_insert(0) += 1;
+    }
+    assert!(tree.iter().all(|(_, count)| *count == 2));
+    for key in (0..1000).step_by(2) {
+        match tree.entry(&key) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), (key, 2)),
+            Entry::Vacant(_) => panic!("missing key {}", key),
+        }
+    }
+    tree.entry(&1).and_modify(|count| *count = 10).or_insert(0);
+    tree.entry(&2).and_modify(|count| *count = 10).or_insert(7);
+    assert_eq!(tree.get(&1), Some(&10));
+    assert_eq!(tree.get(&2), Some(&7));
+    assert_eq!(tree.iter().count(), 501);
+}
+
+fn double<R: DerefMut<Target = u32>>(mut entry: OccupiedEntry<'_, '_, '_, R, u64, u64>) {
+    let value = *entry.get();
+    entry.insert(value * 2);
+}
+
+fn fill<R: DerefMut<Target = u32>>(entry: VacantEntry<'_, '_, '_, R, u64, u64>) {
+    let key = *entry.key();
+    entry.insert(key);
+}
+
+fn sum(iter: Iter<'_, '_, u64, u64>) -> u64 {
+    iter.map(|(_, value)| *value).sum()
+}
+
+#[test]
+fn exported_entry_and_iter_types() {
+    let (_database, mut entries) = in_memory();
+    let mut tree = entries.entries.write();
+    for key in [1, 2, 1] {
+        match tree.entry(&key) {
+            Entry::Occupied(entry) => double(entry),
+            Entry::Vacant(entry) => fill(entry),
+        }
+    }
+    assert_eq!(sum(tree.iter()), 4);
+}
+
+#[test]
+fn in_memory_growth_keeps_pages() {
+    let (mut database, mut entries) = in_memory();
+    let keys = shuffled(100_000, 7);
+    {
+        let mut tree = entries.entries.write();
+        for key in keys.iter() {
+            tree.insert(*key, *key);
+        }
+    }
+    database.snapshot(&mut entries).unwrap();
+    assert!(entries
+        .entries
+        .read()
+        .iter()
+        .map(|(key, value)| (*key, *value))
+        .eq((0..100_000).map(|i| (i, i))));
+}
@@ -0,0 +1,249 @@
+use database::{Database, DatabaseRef, Len, Vec};
+use derive::Object;
+
+const PER_PAGE: usize = 8192 / 8;
+
+const FAN_OUT: usize = 8192 / 4;
+
+#[derive(Object)]
+#[object(application = "vec-test", version = 1)]
+struct Values {
+    values: Vec<u64>,
+}
+
+fn create(len: usize) -> (Database, Values) {
+    let (database, mut values) = Database::in_memory(|database: DatabaseRef| Values {
+        values: Vec::new(database),
+    })
+    .unwrap();
+    values
+        .values
+        .write()
+        .extend((0..len as u64).map(|value| value * 2));
+    (database, values)
+}
+
+fn contents(values: &Vec<u64>) -> std::vec::Vec<u64> {
+    values.read().iter().copied().collect()
+}
+
+#[test]
+fn truncate_clear_and_fill() {
+    let (_database, values) = create(3 * PER_PAGE + 5);
+    let mut values = values.values;
+    let mut expected = contents(&values);
+    values.write().truncate(4 * PER_PAGE);
+    assert_eq!(contents(&values), expected);
+    values.write().truncate(PER_PAGE + 1);
+    expected.truncate(PER_PAGE + 1);
+    assert_eq!(contents(&values), expected);
+    values.write().fill(7);
+    assert!(contents(&values).iter().all(|value| *value == 7));
+    assert_eq!(values.read().len(), PER_PAGE + 1);
+    values.write().clear();
+    assert!(values.read().is_empty());
+    values.write().push(1);
+    assert_eq!(contents(&values), [1]);
+}
+
+#[test]
+fn insert_across_pages() {
+    let (_database, values) = create(2 * PER_PAGE - 1);
+    let mut values = values.values;
+    let mut expected = contents(&values);
+    for (index, value) in [
+        (0, 1),
+        (PER_PAGE - 1, 3),
+        (PER_PAGE, 5),
+        (expected.len() + 1, 9),
+        (2 * PER_PAGE + 1, 11),
+        (PER_PAGE + 17, 13),
+    ] {
+        values.write().insert(index, value);
+        expected.insert(index, value);
+        assert_eq!(contents(&values), expected);
+    }
+}
+
+#[test]
+fn remove_across_pages() {
+    let (_database, values) = create(3 * PER_PAGE + 2);
+    let mut values = values.values;
+    let mut expected = contents(&values);
+    for index in [
+        0,
+        PER_PAGE - 1,
+        PER_PAGE,
+        2 * PER_PAGE + 1,
+        3 * PER_PAGE - 3,
+        2 * PER_PAGE - 1,
+    ] {
+        assert_eq!(values.write().remove(index), expected.remove(index));
+        assert_eq!(contents(&values), expected);
+    }
+    while !expected.is_empty() {
+        let index = expected.len() / 2;
+        assert_eq!(values.write().remove(index), expected.remove(index));
+    }
+    assert!(values.read().is_empty());
+}
+
+#[test]
+fn swap_and_swap_remove() {
+    let (_database, values) = create(PER_PAGE + 3);
+    let mut values = values.values;
+    let mut expected = contents(&values);
+    values.write().swap(1, PER_PAGE + 1);
+    expected.swap(1, PER_PAGE + 1);
+    assert_eq!(contents(&values), expected);
+    assert_eq!(values.write().swap_remove(2), expected.swap_remove(2));
+    assert_eq!(contents(&values), expected);
+    let last = expected.len() - 1;
+    assert_eq!(values.write().swap_remove(last), expected.swap_remove(last));
+    assert_eq!(contents(&values), expected);
+}
+
+#[test]
+fn extend_and_iter_mut() {
+    let (_database, values) = create(0);
+    let mut values = values.values;
+    values.write().extend(0..2 * PER_PAGE as u64 + 1);
+    for value in values.write().iter_mut() {
+        *value *= 3;
+    }
+    assert!(contents(&values)
+        .into_iter()
+        .eq((0..2 * PER_PAGE as u64 + 1).map(|value| value * 3)));
+}
+
+#[test]
+fn binary_search() {
+    let (_database, values) = create(3 * PER_PAGE);
+    let values = values.values.read();
+    assert_eq!(values.binary_search(&0), Ok(0));
+    assert_eq!(values.binary_search(&(2 * PER_PAGE as u64)), Ok(PER_PAGE));
+    assert_eq!(
+        values.binary_search(&(2 * PER_PAGE as u64 + 1)),
+        Err(PER_PAGE + 1)
+    );
+    assert_eq!(values.binary_search(&u64::MAX), Err(3 * PER_PAGE));
+    assert_eq!(
+        values.binary_search_by(|probe| (probe / 2).cmp(&(PER_PAGE as u64 * 2 + 5))),
+        Ok(2 * PER_PAGE + 5)
+    );
+}
+
+#[test]
+fn chunks_follow_pages() {
+    let (_database, values) = create(2 * PER_PAGE + 3);
+    let values = values.values.read();
+    let lens: std::vec::Vec<usize> = values.chunks().map(|chunk| chunk.len()).collect();
+    assert_eq!(lens, [PER_PAGE, PER_PAGE, 3]);
+    assert!(values.chunks().flatten().eq(values.iter()));
+    let (_database, empty) = create(0);
+    assert_eq!(empty.values.read().chunks().count(), 0);
+}
+
+#[test]
+fn copy_to_slice_ranges() {
+    let (_database, values) = create(3 * PER_PAGE + 7);
+    let expected = contents(&values.values);
+    let values = values.values.read();
+    for range in [
+        0..0,
+        PER_PAGE..PER_PAGE,
+        5..17,
+        PER_PAGE - 3..PER_PAGE + 4,
+        3..PER_PAGE,
+        PER_PAGE..2 * PER_PAGE - 1,
+        7..3 * PER_PAGE + 2,
+        0..3 * PER_PAGE + 7,
+    ] {
+        let mut dest = vec![0; range.len()];
+        values.copy_to_slice(range.clone(), &mut dest);
+        assert_eq!(dest, expected[range]);
+    }
+}
+
+#[test]
+fn copy_from_slice_ranges() {
+    let (_database, values) = create(3 * PER_PAGE + 7);
+    let mut expected = contents(&values.values);
+    let mut values = values.values;
+    for (value, range) in [
+        0..0,
+        PER_PAGE..PER_PAGE,
+        5..17,
+        PER_PAGE - 3..PER_PAGE + 4,
+        3..PER_PAGE,
+        PER_PAGE..2 * PER_PAGE - 1,
+        7..3 * PER_PAGE + 2,
+    ]
+    .iter()
+    .cloned()
+    .enumerate()
+    {
+        let src: std::vec::Vec<u64> = range
+            .clone()
+            .map(|index| index as u64 + value as u64)
+            .collect();
+        values.write().copy_from_slice(range.clone(), &src);
+        expected[range].copy_from_slice(&src);
+        assert_eq!(contents(&values), expected);
+    }
+}
+
+#[test]
+fn append_across_pages() {
+    let (_database, values) = create(PER_PAGE - 2);
+    let mut expected = contents(&values.values);
+    let mut values = values.values;
+    for len in [0, 1, 3, PER_PAGE, 2 * PER_PAGE + 5] {
+        let src: std::vec::Vec<u64> = (0..len as u64).map(|value| !value).collect();
+        values.write().append(&src);
+        expected.extend_from_slice(&src);
+        assert_eq!(contents(&values), expected);
+    }
+}
+
+#[test]
+fn grow_and_shrink_across_levels() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("vec.db");
+    {
+        let (mut database, mut values) = Database::create(&path, |database: DatabaseRef| Values {
+            values: Vec::new(database),
+        })
+        .unwrap();
+        let lens = [1, PER_PAGE + 1, FAN_OUT * PER_PAGE + 1];
+        {
+            let mut values = values.values.write();
+            for (stage, len) in lens.iter().enumerate() {
+                values.resize(*len, stage as u64);
+            }
+            assert_eq!(values.len(), lens[2]);
+            assert_eq!(values[0], 0);
+            assert_eq!(values[PER_PAGE], 1);
+            assert_eq!(values[PER_PAGE + 1], 2);
+            assert_eq!(values[lens[2] - 1], 2);
+        }
+        database.snapshot(&mut values).unwrap();
+        {
+            let mut values = values.values.write();
+            values.truncate(lens[1]);
+            assert_eq!(values[PER_PAGE], 1);
+            values.truncate(lens[0]);
+            assert_eq!(values[0], 0);
+            values.resize(lens[2], 3);
+            assert_eq!(values[lens[2] - 1], 3);
+            values.truncate(PER_PAGE);
+        }
+        database.snapshot(&mut values).unwrap();
+    }
+    let report = Database::check::<Values>(&path).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.problems);
+    let (_database, values): (_, Values) = Database::open(&path).unwrap();
+    assert!(contents(&values.values)
+        .into_iter()
+        .eq(std::iter::once(0).chain(std::iter::repeat(3).take(PER_PAGE - 1))));
+}
@@ -0,0 +1,152 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Field, Meta, NestedMeta};
+
+pub fn db_serialize_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    proc_macro::TokenStream::from(db_serialize(&input))
+}
+
+pub fn db_serialize(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let layout = layout(&input.data);
+    let serialize = serialize(&input.data);
+    let deserialize = deserialize(&input.data, false);
+
+    quote! {
+        impl #impl_generics database::DbSerialize for #name #ty_generics #where_clause {
+            const LAYOUT: u64 = #layout;
+
+            fn serialize(&mut self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+                #serialize
+                Ok(())
+            }
+
+            fn deserialize(reader: &mut impl std::io::Read, database: database::DatabaseRef) -> std::io::Result<Self> {
+                #deserialize
+            }
+        }
+    }
+}
+
+fn is_serde(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        attr.path.is_ident("db")
+            && match attr.parse_meta() {
+                Ok(Meta::List(list)) => list.nested.iter().any(|nested| {
+                    matches!(nested, NestedMeta::Meta(Meta::Path(path)) if path.is_ident("serde"))
+                }),
+                _ => false,
+            }
+    })
+}
+
+fn layout(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            syn::Fields::Named(ref fields) => {
+                let mut description = String::new();
+                let mut parts = Vec::new();
+                for f in fields.named.iter() {
+                    let ty = &f.ty;
+                    description.push_str(&f.ident.as_ref().unwrap().to_string());
+                    if is_serde(f) {
+                        description.push_str(":serde;");
+                    } else {
+                        description.push(';');
+                        parts.push(quote_spanned! {f.span() =>
+                            <#ty as database::DbSerialize>::LAYOUT
+                        });
+                    }
+                }
+                quote! {
+                    database::layout(#description, &[#(#parts),*])
+                }
+            }
+            _ => unimplemented!(),
+        },
+        _ => unimplemented!(),
+    }
+}
+
+fn serialize(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            syn::Fields::Named(ref fields) => {
+                let recurse = fields.named.iter().map(|f| {
+                    let name = &f.ident;
+                    if is_serde(f) {
+                        quote_spanned! {f.span() =>
+                            database::serialize_value(&self.#name, writer)?;
+                        }
+                    } else {
+                        quote_spanned! {f.span() =>
+                            database::DbSerialize::serialize(&mut self.#name, writer)?;
+                        }
+                    }
+                });
+                quote! {
+                    #(#recurse)*
+                }
+            }
+            _ => unimplemented!(),
+        },
+        _ => unimplemented!(),
+    }
+}
+
+/// Deserializes the fields of a top-level object, marking where each begins.
+pub fn object_deserialize(input: &DeriveInput) -> TokenStream {
+    deserialize(&input.data, true)
+}
+
+fn deserialize(data: &Data, begin_fields: bool) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            syn::Fields::Named(ref fields) => {
+                let last = fields.named.iter().rposition(|f| !is_serde(f));
+                let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+                    let name = &f.ident;
+                    let ty = &f.ty;
+                    let database = if Some(i) == last {
+                        quote!(database)
+                    } else {
+                        quote!(database.clone())
+                    };
+                    let name_text = name.as_ref().unwrap().to_string();
+                    if is_serde(f) {
+                        quote_spanned! {f.span() =>
+                            #name: database::deserialize_value(reader)?,
+                        }
+                    } else if begin_fields {
+                        quote_spanned! {f.span() =>
+                            #name: {
+                                database.begin_field(#name_text);
+                                <#ty as database::DbSerialize>::deserialize(reader, #database)?
+                            },
+                        }
+                    } else {
+                        quote_spanned! {f.span() =>
+                            #name: <#ty as database::DbSerialize>::deserialize(reader, #database)?,
+                        }
+                    }
+                });
+                let unused = if last.is_none() {
+                    quote!(let _ = database;)
+                } else {
+                    quote!()
+                };
+                quote! {
+                    #unused
+                    Ok(Self {
+                        #(#recurse)*
+                    })
+                }
+            }
+            _ => unimplemented!(),
+        },
+        _ => unimplemented!(),
+    }
+}
@@ -7,6 +7,7 @@ pub fn db_vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     let name = input.ident;
     let vec_name = Ident::new(&format!("{}Vec", name), Span::call_site());
+    let handle_name = Ident::new(&format!("{}Handle", name), Span::call_site());
 
     let members = members(&input.data);
     let new = new(&input.data);
@@ -14,12 +15,20 @@ pub fn db_vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let deserialize = deserialize(&input.data);
     let push = push(&input.data);
     let write = write(&input.data);
+    let get = get(&input.data);
+    let layout = layout(&input.data);
 
     let expanded = quote! {
         pub struct #vec_name {
             #members
-            free: database::Vec<usize>,
-            used: database::Vec<u8>,
+            free: database::Vec<u64>,
+            generations: database::Vec<u32>,
+        }
+
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        pub struct #handle_name {
+            pub index: usize,
+            pub generation: u32,
         }
 
         impl #vec_name {
@@ -28,14 +37,14 @@ pub fn db_vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 Self {
                     #new
                     free: database::Vec::new(database.clone()),
-                    used: database::Vec::new(database)
+                    generations: database::Vec::new(database)
                 }
             }
 
             pub fn serialize(&mut self, mut writer: impl std::io::Write) -> std::io::Result<()> {
                 #serialize
                 self.free.serialize(&mut writer)?;
-                self.used.serialize(&mut writer)?;
+                self.generations.serialize(&mut writer)?;
                 Ok(())
             }
 
@@ -43,32 +52,112 @@ pub fn db_vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 Ok(Self {
                     #deserialize
                     free: database::Vec::deserialize(&mut reader, database.clone())?,
-                    used: database::Vec::deserialize(&mut reader, database.clone())?,
+                    generations: database::Vec::deserialize(&mut reader, database.clone())?,
                 })
             }
 
-            pub fn add(&mut self, mut value: #name) -> usize {
+            pub fn deserialize_legacy(mut reader: impl std::io::Read, database: database::DatabaseRef) -> std::io::Result<Self> {
+                let mut vec = Self {
+                    #deserialize
+                    free: database::Vec::deserialize(&mut reader, database.clone())?,
+                    generations: database::Vec::new(database.clone()),
+                };
+                let mut used = database::Vec::<u8>::deserialize(&mut reader, database)?;
+                vec.generations.write().extend(used.read().iter().map(|used| *used as u32));
+                used.write().clear();
+                Ok(vec)
+            }
+
+            pub fn add(&mut self, mut value: #name) -> #handle_name {
                 use database::Len;
                 let mut free = self.free.write();
-                let mut used = self.used.write();
+                let mut generations = self.generations.write();
                 if free.is_empty() {
                     #push
-                    let index = used.len();
-                    used.push(1);
-                    index
+                    let index = generations.len();
+                    generations.push(1);
+                    #handle_name { index, generation: 1 }
                 } else {
-                    let index = free.pop().unwrap();
+                    let index = free.pop().unwrap() as usize;
                     #write
-                    index
+                    let generation = generations[index].wrapping_add(1);
+                    generations[index] = generation;
+                    #handle_name { index, generation }
                 }
             }
 
-            pub fn free(&mut self, index: usize) {
-                let mut free = self.free.write();
-                let mut used = self.used.write();
-                assert_eq!(used[index], 1);
-                used[index] = 0;
-                free.push(index);
+            pub fn free(&mut self, handle: #handle_name) -> bool {
+                if !self.contains(handle) {
+                    return false;
+                }
+                let index = handle.index;
+                let mut generations = self.generations.write();
+                generations[index] = generations[index].wrapping_add(1);
+                self.free.write().push(index as u64);
+                true
+            }
+
+            pub fn handle(&self, index: usize) -> Option<#handle_name> {
+                use database::Len;
+                let generations = self.generations.read();
+                if index < generations.len() && generations[index] & 1 == 1 {
+                    Some(#handle_name { index, generation: generations[index] })
+                } else {
+                    None
+                }
+            }
+
+            pub fn contains(&self, handle: #handle_name) -> bool {
+                self.handle(handle.index) == Some(handle)
+            }
+
+            pub fn get(&self, handle: #handle_name) -> Option<#name> {
+                if !self.contains(handle) {
+                    return None;
+                }
+                let index = handle.index;
+                Some(#name {
+                    #get
+                })
+            }
+
+            pub fn set(&mut self, handle: #handle_name, value: #name) -> bool {
+                if !self.contains(handle) {
+                    return false;
+                }
+                let index = handle.index;
+                #write
+                true
+            }
+
+            pub fn iter_used(&self) -> impl Iterator<Item = #handle_name> + '_ {
+                use database::Len;
+                let generations = self.generations.read();
+                (0..generations.len()).filter_map(move |index| {
+                    let generation = generations[index];
+                    if generation & 1 == 1 {
+                        Some(#handle_name { index, generation })
+                    } else {
+                        None
+                    }
+                })
+            }
+
+            pub fn len_used(&self) -> usize {
+                use database::Len;
+                self.generations.read().len() - self.free.read().len()
+            }
+        }
+
+        impl database::DbSerialize for #vec_name {
+            const LAYOUT: u64 = #layout;
+
+            fn serialize(&mut self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+                Self::serialize(self, writer)
+            }
+
+            fn deserialize(reader: &mut impl std::io::Read, database: database::DatabaseRef) -> std::io::Result<Self> {
+                Self::deserialize(reader, database)
             }
         }
     };
@@ -164,6 +253,33 @@ fn deserialize(data: &Data) -> TokenStream {
     }
 }
 
+fn layout(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            syn::Fields::Named(ref fields) => {
+                let mut description = String::new();
+                let mut parts = Vec::new();
+                for f in fields.named.iter() {
+                    let ty = &f.ty;
+                    description.push_str(&format!("{};", f.ident.as_ref().unwrap()));
+                    parts.push(quote_spanned! {f.span() =>
+                        <database::Vec<#ty> as database::DbSerialize>::LAYOUT,
+                    });
+                }
+                quote! {
+                    database::layout(#description, &[
+                        #(#parts)*
+                        <database::Vec<u64> as database::DbSerialize>::LAYOUT,
+                        <database::Vec<u32> as database::DbSerialize>::LAYOUT,
+                    ])
+                }
+            }
+            _ => unimplemented!(),
+        },
+        _ => unimplemented!(),
+    }
+}
+
 fn push(data: &Data) -> TokenStream {
     match *data {
         Data::Struct(ref data) => match data.fields {
@@ -203,3 +319,23 @@ fn write(data: &Data) -> TokenStream {
         _ => unimplemented!(),
     }
 }
+
+fn get(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            syn::Fields::Named(ref fields) => {
+                let recurse = fields.named.iter().map(|f| {
+                    let name = &f.ident;
+                    quote_spanned! {f.span() =>
+                        #name: self.#name.read()[index],
+                    }
+                });
+                quote! {
+                    #(#recurse)*
+                }
+            }
+            _ => unimplemented!(),
+        },
+        _ => unimplemented!(),
+    }
+}
@@ -1,5 +1,7 @@
+mod db_serialize;
 mod db_vec;
 mod inspect;
+mod object;
 mod vec;
 
 #[proc_macro_derive(Inspect)]
@@ -16,3 +18,13 @@ pub fn vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 pub fn db_vec_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     db_vec::db_vec_macro(input)
 }
+
+#[proc_macro_derive(DbSerialize, attributes(db))]
+pub fn db_serialize_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    db_serialize::db_serialize_macro(input)
+}
+
+#[proc_macro_derive(Object, attributes(db, object))]
+pub fn object_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    object::object_macro(input)
+}
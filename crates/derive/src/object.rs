@@ -0,0 +1,100 @@
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Expr, Lit, Meta, NestedMeta, Path};
+
+use crate::db_serialize::{db_serialize, object_deserialize};
+
+pub fn object_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let db_serialize = db_serialize(&input);
+    let deserialize = object_deserialize(&input);
+    let (application, version, migrations, legacy) = attributes(&input);
+    let legacy = legacy.map(|legacy| quote! { .with_legacy(#legacy) });
+    let migrations = migrations.map(|migrations| {
+        quote! {
+            fn migrations() -> database::Migrations {
+                #migrations()
+            }
+        }
+    });
+
+    let expanded = quote! {
+        #db_serialize
+
+        impl #impl_generics database::Object for #name #ty_generics #where_clause {
+            fn format() -> database::Format {
+                database::Format::new(#application, #version)#legacy
+            }
+
+            #migrations
+
+            fn serialize(&mut self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+                database::write_layout(&mut writer, <Self as database::DbSerialize>::LAYOUT)?;
+                database::DbSerialize::serialize(self, &mut writer)
+            }
+
+            fn deserialize(mut reader: impl std::io::Read, database: database::DatabaseRef) -> std::io::Result<Self> {
+                database::read_layout(&mut reader, <Self as database::DbSerialize>::LAYOUT)?;
+                let reader = &mut reader;
+                #deserialize
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+fn attributes(input: &DeriveInput) -> (String, u32, Option<Path>, Option<Expr>) {
+    let mut application = None;
+    let mut version = None;
+    let mut migrations = None;
+    let mut legacy = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("object"))
+    {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => panic!("expected #[object(application = \"...\", version = ...)]"),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("application") => {
+                    match value.lit {
+                        Lit::Str(lit) => application = Some(lit.value()),
+                        _ => panic!("object application must be a string"),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("version") => {
+                    match value.lit {
+                        Lit::Int(lit) => version = Some(lit.base10_parse().unwrap()),
+                        _ => panic!("object version must be an integer"),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("migrations") => {
+                    match value.lit {
+                        Lit::Str(lit) => migrations = Some(lit.parse().unwrap()),
+                        _ => panic!("object migrations must be a function path string"),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("legacy") => {
+                    match value.lit {
+                        Lit::Str(lit) => legacy = Some(lit.parse().unwrap()),
+                        _ => panic!("object legacy must be a format id expression string"),
+                    }
+                }
+                _ => panic!("unknown object attribute"),
+            }
+        }
+    }
+    (
+        application.expect("missing object application"),
+        version.expect("missing object version"),
+        migrations,
+        legacy,
+    )
+}
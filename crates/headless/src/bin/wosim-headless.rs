@@ -1,7 +1,7 @@
 use std::{
     ffi::CString,
     fs::read,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -9,12 +9,14 @@ use std::{
     time::Duration,
 };
 
-use ::vulkan::Instance;
+use ::vulkan::{Device, Instance};
+use database::Database;
 use generator::{Generator, Template};
 use headless::error::Error;
 use headless::vulkan::DeviceCandidate;
 use jsonwebtoken::DecodingKey;
 use network::from_pem;
+use persistent::World;
 use semver::Version;
 use server::{Server, ServerConfiguration, ServerType};
 use structopt::StructOpt;
@@ -47,80 +49,137 @@ enum Command {
         decode_key: PathBuf,
     },
     Create,
+    Check {
+        #[structopt(default_value = "world.db")]
+        path: PathBuf,
+    },
+    Compact {
+        #[structopt(default_value = "world.db")]
+        path: PathBuf,
+    },
+    Backup {
+        destination: PathBuf,
+        #[structopt(long, default_value = "world.db")]
+        path: PathBuf,
+    },
 }
 
 impl Command {
     fn run(self) -> Result<(), Error> {
-        let runtime = Runtime::new()?;
-        let version = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
-        let instance = Arc::new(Instance::new(
-            &CString::new("wosim").unwrap(),
-            version,
-            vec![],
-        )?);
-        let device = instance
-            .physical_devices()?
-            .into_iter()
-            .max_ok_filter_map(DeviceCandidate::new)?
-            .ok_or(Error::NoSuitableDeviceFound)?
-            .create()?;
         match self {
             Command::Serve {
                 port,
                 certificate,
                 private_key,
                 decode_key,
-            } => runtime.block_on(async {
-                let running = Arc::new(AtomicBool::new(true));
-                let r = running.clone();
-                ctrlc::set_handler(move || {
-                    r.store(false, Ordering::SeqCst);
+            } => {
+                let _device = create_device()?;
+                Runtime::new()?.block_on(async {
+                    let running = Arc::new(AtomicBool::new(true));
+                    let r = running.clone();
+                    ctrlc::set_handler(move || {
+                        r.store(false, Ordering::SeqCst);
+                    })
+                    .unwrap();
+                    let (certificate_chain, private_key) =
+                        from_pem(certificate, private_key).map_err(Error::FromPem)?;
+                    let rsa_pem = read(decode_key)?;
+                    let decoding_key = DecodingKey::from_rsa_pem(&rsa_pem)
+                        .map_err(Error::InvalidDecodeKey)?
+                        .into_static();
+                    let mut server = Server::new(ServerConfiguration {
+                        port,
+                        r#type: ServerType::Dedicated {
+                            certificate_chain,
+                            private_key,
+                            decoding_key,
+                        },
+                        action_buffer: 64,
+                        request_buffer: 16,
+                        tick_period: Duration::from_millis(50),
+                    })
+                    .map_err(Error::Endpoint)?;
+                    while running.load(Ordering::SeqCst) {
+                        sleep(Duration::from_millis(10)).await;
+                    }
+                    server.stop().await.map_err(Error::Service)?;
+                    Ok(())
                 })
-                .unwrap();
-                let (certificate_chain, private_key) =
-                    from_pem(certificate, private_key).map_err(Error::FromPem)?;
-                let rsa_pem = read(decode_key)?;
-                let decoding_key = DecodingKey::from_rsa_pem(&rsa_pem)
-                    .map_err(Error::InvalidDecodeKey)?
-                    .into_static();
-                let mut server = Server::new(ServerConfiguration {
-                    port,
-                    r#type: ServerType::Dedicated {
-                        certificate_chain,
-                        private_key,
-                        decoding_key,
-                    },
-                    action_buffer: 64,
-                    request_buffer: 16,
-                    tick_period: Duration::from_millis(50),
+            }
+            Command::Create => {
+                let device = create_device()?;
+                Runtime::new()?.block_on(async {
+                    let (sender, mut receiver) = mpsc::channel(16);
+                    let mut generator = Generator::new(Template {}, sender, Arc::new(device));
+                    let control = generator.control.clone();
+                    ctrlc::set_handler(move || {
+                        control.cancel();
+                    })
+                    .unwrap();
+                    while let Some(notification) = receiver.recv().await {
+                        match notification {}
+                    }
+                    generator
+                        .join()
+                        .await
+                        .map_err(|_| Error::NoSuitableDeviceFound)
                 })
-                .map_err(Error::Endpoint)?;
-                while running.load(Ordering::SeqCst) {
-                    sleep(Duration::from_millis(10)).await;
-                }
-                server.stop().await.map_err(Error::Service)?;
-                Ok(())
-            }),
-            Command::Create => runtime.block_on(async {
-                let (sender, mut receiver) = mpsc::channel(16);
-                let mut generator = Generator::new(Template {}, sender, Arc::new(device));
-                let control = generator.control.clone();
-                ctrlc::set_handler(move || {
-                    control.cancel();
-                })
-                .unwrap();
-                while let Some(notification) = receiver.recv().await {
-                    match notification {}
-                }
-                generator
-                    .join()
-                    .await
-                    .map_err(|_| Error::NoSuitableDeviceFound)
-            }),
+            }
+            Command::Check { path } => check(&path),
+            Command::Compact { path } => Ok(Database::compact::<World>(&path)?),
+            Command::Backup { destination, path } => backup(&path, &destination),
         }
     }
 }
 
+fn create_device() -> Result<Device, Error> {
+    let version = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+    let instance = Arc::new(Instance::new(
+        &CString::new("wosim").unwrap(),
+        version,
+        vec![],
+    )?);
+    Ok(instance
+        .physical_devices()?
+        .into_iter()
+        .max_ok_filter_map(DeviceCandidate::new)?
+        .ok_or(Error::NoSuitableDeviceFound)?
+        .create()?)
+}
+
+fn check(path: &Path) -> Result<(), Error> {
+    let report = Database::check::<World>(path)?;
+    println!(
+        "{}: version {}, {} pages ({} used, {} free, {} retained)",
+        path.display(),
+        report.version,
+        report.pages,
+        report.used,
+        report.free,
+        report.retained
+    );
+    for problem in report.problems.iter() {
+        println!("{}", problem);
+    }
+    if report.is_consistent() {
+        Ok(())
+    } else {
+        Err(Error::InconsistentDatabase(report.problems.len()))
+    }
+}
+
+fn backup(path: &Path, destination: &Path) -> Result<(), Error> {
+    let backup = Database::backup::<World>(path, destination)?;
+    println!(
+        "{}: version {}, {} pages ({} bytes)",
+        destination.display(),
+        backup.version,
+        backup.pages,
+        backup.bytes
+    );
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
     tracing_subscriber::fmt().init();
     Command::from_args().run()
@@ -13,6 +13,7 @@ pub enum Error {
     NoSuitableDeviceFound,
     FromPem(FromPemError),
     InvalidDecodeKey(jsonwebtoken::errors::Error),
+    InconsistentDatabase(usize),
 }
 
 impl From<vulkan::Error> for Error {
@@ -4,7 +4,6 @@ use protocol::{Position, RegionPos, Rotation};
 #[derive(DbVec)]
 pub struct NPC {
     pub region: RegionPos,
-    pub region_index: usize,
     pub position: Position,
     pub rotation: Rotation,
 }
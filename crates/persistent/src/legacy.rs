@@ -0,0 +1,112 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use database::{DatabaseRef, Len, Object, OneToMany, Tree};
+use derive::DbVec;
+use protocol::{Position, RegionPos, Rotation, SLOT_COUNT};
+
+use crate::{Configuration, NPCVec, PCSlot, PCVec, Player, PlayerVec, World, NPC};
+
+#[derive(DbVec)]
+#[allow(dead_code)]
+pub struct LegacyNPC {
+    pub region: RegionPos,
+    pub region_index: usize,
+    pub position: Position,
+    pub rotation: Rotation,
+}
+
+impl LegacyNPCVec {
+    fn migrate(mut self, database: DatabaseRef) -> NPCVec {
+        let mut npcs = NPCVec::new(database);
+        let len = self.generations.read().len();
+        for index in 0..len {
+            npcs.add(NPC {
+                region: self.region.read()[index],
+                position: self.position.read()[index],
+                rotation: self.rotation.read()[index],
+            });
+        }
+        for index in self.free.read().iter() {
+            if let Some(handle) = npcs.handle(*index as usize) {
+                npcs.free(handle);
+            }
+        }
+        self.region.write().clear();
+        self.region_index.write().clear();
+        self.position.write().clear();
+        self.rotation.write().clear();
+        self.free.write().clear();
+        self.generations.write().clear();
+        npcs
+    }
+}
+
+#[derive(DbVec)]
+#[allow(dead_code)]
+pub struct LegacyPlayer {
+    pub slots: [u32; SLOT_COUNT],
+}
+
+impl LegacyPlayerVec {
+    fn migrate(mut self, pcs: &PCVec, database: DatabaseRef) -> PlayerVec {
+        let mut players = PlayerVec::new(database);
+        let len = self.generations.read().len();
+        for index in 0..len {
+            let mut slots = [PCSlot::EMPTY; SLOT_COUNT];
+            for (slot, id) in slots.iter_mut().zip(self.slots.read()[index].iter()) {
+                if let Some(handle) = pcs.handle(*id as usize) {
+                    *slot = handle.into();
+                }
+            }
+            players.add(Player { slots });
+        }
+        for index in self.free.read().iter() {
+            if let Some(handle) = players.handle(*index as usize) {
+                players.free(handle);
+            }
+        }
+        self.slots.write().clear();
+        self.free.write().clear();
+        self.generations.write().clear();
+        players
+    }
+}
+
+/// Converts the world content written before schema versioning, which
+/// stored plain region npc lists and slot based containers.
+pub fn migrate_v0(
+    mut reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    database: DatabaseRef,
+) -> io::Result<()> {
+    let configuration: Configuration = database::deserialize_value(&mut reader)?;
+    let heights = database::Vec::deserialize(&mut reader, database.clone())?;
+    let npcs = LegacyNPCVec::deserialize_legacy(&mut reader, database.clone())?;
+    let pcs = PCVec::deserialize_legacy(&mut reader, database.clone())?;
+    let players = LegacyPlayerVec::deserialize_legacy(&mut reader, database.clone())?;
+    let player_index = Tree::deserialize(&mut reader, database.clone())?;
+    let size = (configuration.size as usize).pow(2);
+    let mut region_npcs = OneToMany::new(database.clone(), size);
+    for group in 0..size {
+        let mut members = database::Vec::<usize>::deserialize(&mut reader, database.clone())?;
+        for member in members.read().iter() {
+            if !region_npcs.insert(group, *member) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "npc is listed in more than one region",
+                ));
+            }
+        }
+        members.write().clear();
+    }
+    let mut world = World {
+        configuration,
+        heights,
+        npcs: npcs.migrate(database.clone()),
+        players: players.migrate(&pcs, database),
+        pcs,
+        player_index,
+        region_npcs,
+    };
+    Object::serialize(&mut world, writer)
+}
@@ -1,11 +1,10 @@
 mod character;
 mod configuration;
+mod legacy;
 mod player;
-mod region;
 mod world;
 
 pub use character::*;
 pub use configuration::*;
 pub use player::*;
-pub use region::*;
 pub use world::*;
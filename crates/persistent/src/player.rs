@@ -1,7 +1,46 @@
+use bytemuck::{Pod, Zeroable};
 use derive::DbVec;
-use protocol::PlayerSlots;
+use protocol::SLOT_COUNT;
+
+use crate::PCHandle;
 
 #[derive(DbVec)]
 pub struct Player {
-    pub slots: PlayerSlots,
+    pub slots: [PCSlot; SLOT_COUNT],
+}
+
+/// Handle of the character bound to a player slot. Live generations are
+/// odd, so the zeroed value never matches a character and marks a free slot.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct PCSlot {
+    index: u32,
+    generation: u32,
+}
+
+impl PCSlot {
+    pub const EMPTY: Self = Self {
+        index: 0,
+        generation: 0,
+    };
+
+    pub fn handle(self) -> Option<PCHandle> {
+        if self == Self::EMPTY {
+            None
+        } else {
+            Some(PCHandle {
+                index: self.index as usize,
+                generation: self.generation,
+            })
+        }
+    }
+}
+
+impl From<PCHandle> for PCSlot {
+    fn from(handle: PCHandle) -> Self {
+        Self {
+            index: handle.index as u32,
+            generation: handle.generation,
+        }
+    }
 }
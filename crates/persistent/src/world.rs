@@ -1,32 +1,39 @@
-use std::{io::ErrorKind, mem::swap};
+use database::{DatabaseRef, Entry, Migrations, OneToMany, Tree};
+use derive::Object;
+use protocol::{PlayerSlots, Position, Rotation, SLOT_COUNT};
 
-use database::{DatabaseRef, Entry, Format, Len, Object, Tree};
-use protocol::{Position, Rotation, SLOT_COUNT};
-
-use crate::{Configuration, NPCVec, PCVec, Player, PlayerVec, Region, NPC, PC};
+use crate::{
+    legacy::migrate_v0, Configuration, NPCVec, PCHandle, PCSlot, PCVec, Player, PlayerVec, NPC, PC,
+};
 
+#[derive(Object)]
+#[object(
+    application = "wosim-world",
+    version = 1,
+    migrations = "migrations",
+    legacy = "[64; 256]"
+)]
 pub struct World {
+    #[db(serde)]
+    pub configuration: Configuration,
     pub heights: database::Vec<u8>,
     pub npcs: NPCVec,
     pub pcs: PCVec,
     pub players: PlayerVec,
     pub player_index: Tree<u128, u32>,
-    pub regions: Vec<Region>,
-    pub configuration: Configuration,
+    pub region_npcs: OneToMany,
 }
 
 impl World {
     pub fn new(database: DatabaseRef, configuration: Configuration) -> Self {
         let size = (configuration.size as usize).pow(2);
-        let mut regions = Vec::with_capacity(size);
-        regions.resize_with(size, || Region::new(database.clone()));
         Self {
             heights: database::Vec::new(database.clone()),
             npcs: NPCVec::new(database.clone()),
             pcs: PCVec::new(database.clone()),
             players: PlayerVec::new(database.clone()),
-            player_index: Tree::new(database),
-            regions,
+            player_index: Tree::new(database.clone()),
+            region_npcs: OneToMany::new(database, size),
             configuration,
         }
     }
@@ -34,9 +41,13 @@ impl World {
     pub fn initialize_player(&mut self, uuid: u128) {
         let mut player_index = self.player_index.write();
         if let Entry::Vacant(vacant) = player_index.entry(&uuid) {
-            vacant.insert(self.players.add(Player {
-                slots: [u32::MAX; SLOT_COUNT],
-            }) as u32);
+            vacant.insert(
+                self.players
+                    .add(Player {
+                        slots: [PCSlot::EMPTY; SLOT_COUNT],
+                    })
+                    .index as u32,
+            );
         }
     }
 
@@ -46,17 +57,16 @@ impl World {
         let x = (position.x as usize).clamp(0, size - 1);
         let z = (position.z as usize).clamp(0, size - 1);
         position.y = self.heights.read()[z * size + x] as f32 + 1.0;
-        let id = self.npcs.add(NPC {
-            position,
-            rotation,
-            region: region_pos,
-            region_index: usize::MAX,
-        });
-        let mut npcs = self.regions[region_pos.into_index(self.configuration.size) as usize]
+        let id = self
             .npcs
-            .write();
-        self.npcs.region_index.write()[id] = npcs.len();
-        npcs.push(id);
+            .add(NPC {
+                position,
+                rotation,
+                region: region_pos,
+            })
+            .index;
+        self.region_npcs
+            .insert(region_pos.into_index(self.configuration.size) as usize, id);
         id
     }
 
@@ -66,9 +76,8 @@ impl World {
         rotation: Rotation,
         player: u32,
         slot: u8,
-    ) -> Option<usize> {
-        let slot_container = &mut self.players.slots.write()[player as usize][slot as usize];
-        if *slot_container != u32::MAX {
+    ) -> Option<PCHandle> {
+        if self.pc(player, slot).is_some() {
             return None;
         }
         let region_pos = self.configuration.region(position);
@@ -76,67 +85,43 @@ impl World {
         let x = (position.x as usize).clamp(0, size - 1);
         let z = (position.z as usize).clamp(0, size - 1);
         position.y = self.heights.read()[z * size + x] as f32 + 1.0;
-        let id = self.pcs.add(PC {
+        let handle = self.pcs.add(PC {
             position,
             rotation,
             region: region_pos,
             player,
             slot,
         });
-        *slot_container = id as u32;
-        Some(id)
+        self.players.slots.write()[player as usize][slot as usize] = handle.into();
+        Some(handle)
     }
 
-    pub fn delete_pc(&mut self, player: u32, slot: u8) {
-        let mut slots = self.players.slots.write();
-        let mut new_slot = u32::MAX;
-        swap(&mut new_slot, &mut slots[player as usize][slot as usize]);
-        if new_slot != u32::MAX {
-            self.pcs.free(new_slot as usize);
+    pub fn pc(&self, player: u32, slot: u8) -> Option<PCHandle> {
+        let handle = self.players.slots.read()[player as usize][slot as usize].handle()?;
+        if self.pcs.contains(handle) {
+            Some(handle)
+        } else {
+            None
         }
     }
-}
 
-impl Object for World {
-    fn format() -> Format {
-        [64; 256]
-    }
-
-    fn serialize(&mut self, mut writer: impl std::io::Write) -> std::io::Result<()> {
-        bincode::serialize_into(&mut writer, &self.configuration)
-            .map_err(|_| std::io::Error::new(ErrorKind::Other, "oh no!"))?;
-        self.heights.serialize(&mut writer)?;
-        self.npcs.serialize(&mut writer)?;
-        self.pcs.serialize(&mut writer)?;
-        self.players.serialize(&mut writer)?;
-        self.player_index.serialize(&mut writer)?;
-        for region in self.regions.iter_mut() {
-            region.serialize(&mut writer)?;
+    pub fn player_slots(&self, player: u32) -> PlayerSlots {
+        let mut slots = [u32::MAX; SLOT_COUNT];
+        for (slot, id) in slots.iter_mut().enumerate() {
+            if let Some(handle) = self.pc(player, slot as u8) {
+                *id = handle.index as u32;
+            }
         }
-        Ok(())
+        slots
     }
 
-    fn deserialize(mut reader: impl std::io::Read, database: DatabaseRef) -> std::io::Result<Self> {
-        let configuration: Configuration = bincode::deserialize_from(&mut reader)
-            .map_err(|_| std::io::Error::new(ErrorKind::Other, "oh no!"))?;
-        let heights = database::Vec::deserialize(&mut reader, database.clone())?;
-        let npcs = NPCVec::deserialize(&mut reader, database.clone())?;
-        let pcs = PCVec::deserialize(&mut reader, database.clone())?;
-        let players = PlayerVec::deserialize(&mut reader, database.clone())?;
-        let player_index = Tree::deserialize(&mut reader, database.clone())?;
-        let size = (configuration.size as usize).pow(2);
-        let mut regions = Vec::with_capacity(size);
-        for _ in 0..size {
-            regions.push(Region::deserialize(&mut reader, database.clone())?)
-        }
-        Ok(Self {
-            heights,
-            npcs,
-            pcs,
-            players,
-            player_index,
-            configuration,
-            regions,
-        })
+    pub fn delete_pc(&mut self, player: u32, slot: u8) -> bool {
+        let handle = self.players.slots.read()[player as usize][slot as usize].handle();
+        self.players.slots.write()[player as usize][slot as usize] = PCSlot::EMPTY;
+        handle.map_or(false, |handle| self.pcs.free(handle))
     }
 }
+
+fn migrations() -> Migrations {
+    Migrations::new().with(0, migrate_v0)
+}
@@ -38,11 +38,10 @@ pub fn dynamic_setup(
     physics: &mut physics::World,
     tick: usize,
 ) {
-    let region =
-        &mut persistent.regions[region_pos.into_index(persistent.configuration.size) as usize];
+    let group = region_pos.into_index(persistent.configuration.size) as usize;
     let positions = persistent.npcs.position.read();
     let rotations = persistent.npcs.rotation.read();
-    for id in region.npcs.read().iter().cloned() {
+    for id in persistent.region_npcs.iter(group) {
         let pos = positions[id];
         let rotation = rotations[id];
         let transform = Isometry::from_parts(
@@ -72,11 +71,10 @@ pub fn dynamic_teardown(
     persistent: &mut World,
     physics: &mut physics::World,
 ) {
-    let region =
-        &mut persistent.regions[region_pos.into_index(persistent.configuration.size) as usize];
+    let group = region_pos.into_index(persistent.configuration.size) as usize;
     let mut positions = persistent.npcs.position.write();
     let mut rotations = persistent.npcs.rotation.write();
-    for id in region.npcs.read().iter().cloned() {
+    for id in persistent.region_npcs.iter(group) {
         let npc = transient.npcs.remove_by_id(id).unwrap();
         let transform = npc.transform.last().0;
         positions[id] = transform.translation.into();
@@ -286,19 +284,14 @@ impl RegionManager {
                     static_setup(pos, persistent, transient, physics);
                     Region::default()
                 });
-                let mut heights = Vec::new();
                 let heights_src = persistent.heights.read();
-                let size = (persistent.configuration.region_size as usize)
-                    * (persistent.configuration.size as usize)
-                    + 1;
-                for z in 0..persistent.configuration.region_size + 1 {
-                    let z = (pos.z as usize) * (persistent.configuration.region_size as usize)
-                        + (z as usize);
-                    for x in 0..persistent.configuration.region_size + 1 {
-                        let x = (pos.x as usize) * (persistent.configuration.region_size as usize)
-                            + (x as usize);
-                        heights.push(heights_src[z * size + x])
-                    }
+                let region_size = persistent.configuration.region_size as usize;
+                let size = region_size * (persistent.configuration.size as usize) + 1;
+                let mut heights = vec![0; (region_size + 1) * (region_size + 1)];
+                for (z, row) in heights.chunks_mut(region_size + 1).enumerate() {
+                    let start = ((pos.z as usize) * region_size + z) * size
+                        + (pos.x as usize) * region_size;
+                    heights_src.copy_to_slice(start..start + region_size + 1, row);
                 }
                 let _ = sender
                     .send(Message::from(Notification::StaticSetup((
@@ -321,11 +314,9 @@ impl RegionManager {
                 observer.level = UpdateLevel::Full;
                 let mut npcs = Vec::new();
                 let mut pcs = Vec::new();
-                for id in persistent.regions[pos.into_index(persistent.configuration.size) as usize]
-                    .npcs
-                    .read()
-                    .iter()
-                    .cloned()
+                for id in persistent
+                    .region_npcs
+                    .iter(pos.into_index(persistent.configuration.size) as usize)
                 {
                     let index = transient.npcs.index[&id];
                     let transform = transient.npcs.transform[index].last().0;
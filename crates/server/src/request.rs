@@ -63,14 +63,14 @@ pub(crate) async fn handle_request(
         Request::Slots(sender) => {
             let player_id = player_id(world, user.uuid);
             sender
-                .send(world.persistent.players.slots.read()[player_id as usize])
+                .send(world.persistent.player_slots(player_id))
                 .unwrap();
         }
         Request::Create(slot, sender) => {
             validate_slot(slot)?;
             let player_id = player_id(world, user.uuid);
             let size = world.persistent.configuration.full_size() as f32;
-            if let Some(id) = world.persistent.spawn_pc(
+            if let Some(handle) = world.persistent.spawn_pc(
                 vec3(size / 2.0, 0.0, size / 2.0),
                 Rotation {
                     roll: 0.0,
@@ -80,7 +80,7 @@ pub(crate) async fn handle_request(
                 player_id,
                 slot,
             ) {
-                sender.send(id as u32).unwrap();
+                sender.send(handle.index as u32).unwrap();
             } else {
                 return Err(RequestError::SlotAlreadyBound);
             }
@@ -97,12 +97,10 @@ pub(crate) async fn handle_request(
             match world.observers.entry(user.uuid) {
                 Entry::Occupied(_) => return Err(RequestError::AlreadyInGame),
                 Entry::Vacant(entry) => {
-                    let id =
-                        world.persistent.players.slots.read()[player_id as usize][slot as usize];
-                    if id == u32::MAX {
-                        return Err(RequestError::SlotNotBound);
-                    }
-                    let id = id as usize;
+                    let id = match world.persistent.pc(player_id, slot) {
+                        Some(handle) => handle.index,
+                        None => return Err(RequestError::SlotNotBound),
+                    };
                     let pos = world.persistent.pcs.position.read()[id];
                     let rotation = world.persistent.pcs.rotation.read()[id];
                     let transform =
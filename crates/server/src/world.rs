@@ -5,7 +5,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use database::{add_mapping, remove_mapping, Database};
+use database::Database;
 use itertools::izip;
 use nalgebra::Isometry3;
 use network::Message;
@@ -155,24 +155,12 @@ impl ServerWorld {
                 let last_region = self.persistent.configuration.region(last_position);
                 let current_region = self.persistent.configuration.region(current_position);
                 if current_region != last_region {
-                    remove_mapping(
-                        &mut self.persistent.npcs.region_index.write(),
-                        id,
-                        &mut self.persistent.regions
-                            [last_region.into_index(self.persistent.configuration.size) as usize]
-                            .npcs
-                            .write(),
-                    );
                     self.persistent.npcs.region.write()[id] = current_region;
-                    add_mapping(
-                        &mut self.persistent.npcs.region_index.write(),
+                    let moved = self.persistent.region_npcs.move_to(
                         id,
-                        &mut self.persistent.regions[current_region
-                            .into_index(self.persistent.configuration.size)
-                            as usize]
-                            .npcs
-                            .write(),
+                        current_region.into_index(self.persistent.configuration.size) as usize,
                     );
+                    debug_assert!(moved, "npc {} is not in a region", id);
                     self.regions
                         .get_mut(last_region)
                         .unwrap()